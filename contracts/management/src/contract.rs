@@ -2,17 +2,18 @@
 
 use linera_sdk::{
     linera_base_types::{
-        Amount, WithContractAbi, StreamUpdate, ChainId
+        Amount, WithContractAbi, StreamUpdate, ChainId, ApplicationId, Account, AccountOwner
     },
     views::{RootView, View},
     Contract, ContractRuntime,
 };
 
 use management::{
-    Operation, Message, Bet, Event,
-    state::{ManagementState, LeaderboardWinner, MatchStatus, TeamInfo,PredictionType, UserVotes, LivePrediction, Vote, Teams, Team, Odds, MatchResult, TypeEvent, UserOdd, UserOdds, Selection, BetStatus, LiveScore, MatchEvent, MatchEventType, UserStats}
+    Operation, Message, Bet, Event, InstantiationArgument, ManagementParameters,
+    state::{ManagementState, BouncedStake, LedgerDirection, LedgerEntry, LedgerReason, LeaderboardWinner, LeaderboardRound, MatchStatus, PredictionType, UserVotes, LivePrediction, Vote, Teams, Team, Odds, MatchResult, UserOdd, UserOdds, Selection, BetStatus, LiveScore, MatchEvent, MatchEventType, UserStats, OddsChangeReason, OddsPoint, SelectionStakes, UnpaidPayout, PendingBet, PricingBase, MatchState, payout, SCHEMA_VERSION}
 };
-use std::collections::HashMap;
+use management::odds::{calculate_odds, carry_odds, get_market_odds, max_stake, skew_for_liability};
+use usdl::{UsdlAbi, Operation as UsdlOperation};
 use std::str::FromStr;
 const STREAM_NAME: &[u8] = b"bets";
 pub struct ManagementContract {
@@ -28,19 +29,37 @@ impl WithContractAbi for ManagementContract {
 
 impl Contract for ManagementContract {
     type Message = Message;
-    type Parameters = ();
-    type InstantiationArgument = ();
+    type Parameters = ManagementParameters;
+    type InstantiationArgument = InstantiationArgument;
     type EventValue = Bet;
 
-    async fn load(runtime: ContractRuntime<Self>) -> Self {
-        let state = ManagementState::load(runtime.root_view_storage_context())
+    async fn load(mut runtime: ContractRuntime<Self>) -> Self {
+        let mut state = ManagementState::load(runtime.root_view_storage_context())
             .await
             .expect("Failed to load state");
+        if *state.schema_version.get() < SCHEMA_VERSION {
+            state.migrate(&runtime.application_parameters()).await;
+        }
         ManagementContract { state, runtime }
     }
 
-    async fn instantiate(&mut self, _argument: Self::InstantiationArgument) {
-        self.runtime.application_parameters();
+    async fn instantiate(&mut self, argument: Self::InstantiationArgument) {
+        // Reject parameters the odds engine and the leaderboard payout cannot work with
+        let params = self.runtime.application_parameters();
+        assert!(params.min_odd <= params.max_odd, "min_odd must not be above max_odd");
+        assert!(params.margin >= 100, "margin must be at least 100, odds would pay more than fair");
+        assert!(params.leaderboard_split.iter().sum::<u64>() <= 100, "leaderboard_split must not add up to more than 100");
+
+        self.state.oracles.set(argument.oracles);
+        self.state.admin.set(Some(argument.admin));
+
+        // Seed the house funds held by this application in USDL, bonuses and winnings are paid from them
+        assert!(argument.initial_treasury > Amount::ZERO, "The house needs an initial treasury");
+        let usdl_app_id = self.usdl_app_id();
+        let minter = self.runtime.authenticated_signer().expect("Creating the application requires a signer");
+        let target_account = self.house_account();
+        self.runtime.call_application(true, usdl_app_id, &UsdlOperation::Mint { amount: argument.initial_treasury });
+        self.runtime.call_application(true, usdl_app_id, &UsdlOperation::Transfer { owner: minter, amount: argument.initial_treasury, target_account });
     }
 
     async fn execute_operation(&mut self, operation: Self::Operation) -> Self::Response {
@@ -56,6 +75,25 @@ impl Contract for ManagementContract {
                 self.runtime
                     .unsubscribe_from_events(chain_id, app_id, STREAM_NAME.into());
             },
+            //admin operations
+            Operation::AddOracle { chain_id } => {
+                self.assert_admin();
+                let mut oracles = self.state.oracles.get().clone();
+                if !oracles.contains(&chain_id) {
+                    oracles.push(chain_id);
+                }
+                self.state.oracles.set(oracles);
+            },
+            Operation::RemoveOracle { chain_id } => {
+                self.assert_admin();
+                let mut oracles = self.state.oracles.get().clone();
+                oracles.retain(|oracle| *oracle != chain_id);
+                self.state.oracles.set(oracles);
+            },
+            Operation::TransferAdmin { new_admin } => {
+                self.assert_admin();
+                self.state.admin.set(Some(new_admin));
+            },
             Operation::UpdateTeamPower { team_id, name, power, form, goal_average } => {
                 let management_chain_id = self.runtime.application_creator_chain_id();
                 let mut team = self.state.power_ranking.get(&team_id).await.expect("Team not found").unwrap_or_default();
@@ -72,33 +110,23 @@ impl Contract for ManagementContract {
                 ).with_authentication().send_to(management_chain_id);
             },
             Operation::UpdateEventStatus { event_id, status } => {
-                //assert!(self.runtime.chain_id() != self.runtime.application_creator_chain_id());
                 let management_chain_id = self.runtime.application_creator_chain_id();
                 let mut event = self.state.events.get(&event_id).await.expect("Event not found").unwrap();
-                
-                let new_status = match status.as_str() {
-                    "Scheduled" => MatchStatus::Scheduled,
-                    "Live" => MatchStatus::Live,
-                    "Finished" => MatchStatus::Finished,
-                    "Postponed" => MatchStatus::Postponed,
-                    _ => MatchStatus::Scheduled,
-                };
 
-                event.status = new_status;
+                event.status = status;
                 event.last_updated = self.runtime.system_time();
                 let _ = self.state.events.insert(&event_id, event.clone());
                 
                 self.runtime.prepare_message(
-                    Message::EventStatusUpdated { event_id: event_id.clone(), status: new_status }
+                    Message::EventStatusUpdated { event_id: event_id.clone(), status }
                 ).with_authentication().send_to(management_chain_id);
             },
             Operation::UpdateEventLiveScore { event_id, home_score, away_score } => {
-                //assert!(self.runtime.chain_id() != self.runtime.application_creator_chain_id());
                 let management_chain_id = self.runtime.application_creator_chain_id();
                 let mut event = self.state.events.get(&event_id).await.expect("Event not found").unwrap();
                 let live_score = LiveScore {
-                    home: home_score.clone(),
-                    away: away_score.clone(),
+                    home: home_score,
+                    away: away_score,
                     updated_at: self.runtime.system_time(),
                 };
                 event.live_score = live_score;
@@ -120,25 +148,32 @@ impl Contract for ManagementContract {
                     Message::EventMinuteUpdated { event_id: event_id.clone(), minute: current_minute }
                 ).with_authentication().send_to(management_chain_id);
             },
+            Operation::UpdateEventOdds { event_id, home_odds, away_odds, tie_odds } => {
+                let management_chain_id = self.runtime.application_creator_chain_id();
+                let mut event = self.state.events.get(&event_id).await.expect("Event not found").unwrap();
+
+                event.odds = Odds { home: home_odds, away: away_odds, tie: tie_odds };
+                event.last_updated = self.runtime.system_time();
+                let _ = self.state.events.insert(&event_id, event);
+
+                self.runtime.prepare_message(
+                    Message::EventOddsUpdated { event_id: event_id.clone(), home: home_odds, away: away_odds, tie: tie_odds }
+                ).with_authentication().send_to(management_chain_id);
+            },
+            Operation::SuspendMarket { event_id } => {
+                self.set_market_suspended(event_id, true).await;
+            },
+            Operation::ResumeMarket { event_id } => {
+                self.set_market_suspended(event_id, false).await;
+            },
             Operation::AddMatchEvent { event_id, event_type, time, team, player, detail, timestamp } => {
-                //assert!(self.runtime.chain_id() != self.runtime.application_creator_chain_id());
                 let management_chain_id = self.runtime.application_creator_chain_id();
+                assert!(event_type != MatchEventType::None, "Invalid match event type");
                 let mut event = self.state.events.get(&event_id).await.expect("Event not found").unwrap();
                 
-                // Parse event_type string to enum
-                let event_type_enum = match event_type.as_str() {
-                    "Goal" => MatchEventType::Goal,
-                    "YellowCard" => MatchEventType::YellowCard,
-                    "RedCard" => MatchEventType::RedCard,
-                    "Substitution" => MatchEventType::Substitution,
-                    "Corner" => MatchEventType::Corner,
-                    "Penalty" => MatchEventType::Penalty,
-                    _ => MatchEventType::None,
-                };
-                
                 // Construct MatchEvent from individual parameters
                 let match_event_local = MatchEvent {
-                    event_type: event_type_enum,
+                    event_type,
                     time: time.clone(),
                     team: team.clone(),
                     player: player.clone(),
@@ -147,11 +182,14 @@ impl Contract for ManagementContract {
                 };
                 
                 event.match_events.push(match_event_local);
+                if event_type == MatchEventType::Goal && event.status == MatchStatus::Live {
+                    event.suspended = true;
+                }
                 let _ = self.state.events.insert(&event_id, event);
 
                 // Actually, the message takes the whole struct.
                 let match_event_msg = MatchEvent {
-                    event_type: event_type_enum,
+                    event_type,
                     time,
                     team,
                     player,
@@ -164,26 +202,19 @@ impl Contract for ManagementContract {
                 ).with_authentication().send_to(management_chain_id);
             },
             Operation::CreateEvent { id, type_event, league, home_id, away_id, start_time } => {
-                //assert_eq!(self.runtime.chain_id(), self.runtime.application_creator_chain_id());
                 let management_chain_id = self.runtime.application_creator_chain_id();
-                let type_eventE = match type_event.as_str() {
-                    "Football" => TypeEvent::Football,
-                    "Esports" => TypeEvent::Esports,
-                    "Baseball" => TypeEvent::Baseball,
-                    _ => TypeEvent::Football,
-                };
-
                 //get home and away team info from power ranking
                 let home_team = self.state.power_ranking.get(&home_id).await.expect("Home team not found").unwrap();
                 let away_team = self.state.power_ranking.get(&away_id).await.expect("Away team not found").unwrap();
 
                 //calculate odds from team power ranking
-                let odds = calculate_odds(&home_team, &away_team);
+                let params = self.runtime.application_parameters();
+                let odds = calculate_odds(&home_team, &away_team, &params);
 
                 let event = Event {
                     id : id.clone(),
                     status: MatchStatus::Scheduled,
-                    type_event: type_eventE,
+                    type_event,
                     league: league.clone(),
                     teams: Teams { 
                         home: Team { 
@@ -202,7 +233,7 @@ impl Contract for ManagementContract {
                     match_events: Vec::new(),
                     last_updated: self.runtime.system_time(),
                     current_minute: Some(0),
-                    predictions: Vec::new(),
+                    suspended: false,
                 };
 
                 let _ = self.state.events.insert(&id.clone(), event.clone());
@@ -213,33 +244,31 @@ impl Contract for ManagementContract {
             },
             Operation::ResolveEvent { event_id, winner, home_score, away_score } => {
                 let management_chain_id = self.runtime.application_creator_chain_id();
-                //assert_eq!(self.runtime.chain_id(), management_chain_id);
 
+                assert_eq!(
+                    winner,
+                    Selection::from_score(home_score, away_score),
+                    "Winner does not match the final score"
+                );
                 let mut event = self.state.events.get(&event_id).await.expect("Event not found").unwrap();
-                let winner_enum = match winner.as_str() {
-                    "Home" => Selection::Home,
-                    "Away" => Selection::Away,
-                    _ => Selection::Tie,
-                };
                 let result = MatchResult {
-                    winner: winner_enum,
-                    home_score: home_score.clone(),
-                    away_score: away_score.clone(),
+                    winner,
+                    home_score,
+                    away_score,
                 };
 
                 event.result = result;
                 event.status = MatchStatus::Finished;
 
                 // Resolve TotalGoalsUnder predictions
-                let h_score = home_score.parse::<u8>().unwrap_or(0);
-                let a_score = away_score.parse::<u8>().unwrap_or(0);
-                let total_goals = h_score + a_score;
+                let total_goals = home_score.saturating_add(away_score);
 
-                for prediction in event.predictions.iter_mut().filter(|p| !p.resolved) {
+                for mut prediction in self.open_predictions(&event_id).await {
                     if let PredictionType::TotalGoalsUnder(threshold) = prediction.prediction_type {
                          let outcome = total_goals < threshold;
                          prediction.resolved = true;
                          prediction.outcome = Some(outcome);
+                         let _ = self.state.live_predictions.insert(&(event_id.clone(), prediction.id), prediction);
                     }
                 }
 
@@ -247,17 +276,30 @@ impl Contract for ManagementContract {
                 let _ = self.state.events.insert(&event_id, event);
 
                 self.runtime.prepare_message(
-                    Message::EventOutcomeResolved { event_id: event_id.clone(), winner: winner_enum, home_score, away_score }
+                    Message::EventOutcomeResolved { event_id: event_id.clone(), winner, home_score, away_score }
+                ).with_authentication().send_to(management_chain_id);
+            },
+            Operation::VoidEvent { event_id } => {
+                let management_chain_id = self.runtime.application_creator_chain_id();
+                let mut event = self.state.events.get(&event_id).await.expect("Event not found").unwrap();
+                assert!(event.status != MatchStatus::Finished, "Cannot void a resolved event");
+
+                event.status = MatchStatus::Cancelled;
+                event.last_updated = self.runtime.system_time();
+                let _ = self.state.events.insert(&event_id, event);
+
+                self.runtime.prepare_message(
+                    Message::EventVoided { event_id: event_id.clone(), status: MatchStatus::Cancelled }
                 ).with_authentication().send_to(management_chain_id);
             },
             //leaderboad operations.
             Operation::StartNewWeek{ week, year, prize_pool } =>{
                 let management_chain_id = self.runtime.application_creator_chain_id();
                 //copy oracle chain and set new week
-                let mut leaderboard_data = self.state.leaderboard.get().clone();
-                leaderboard_data.week = week;
-                leaderboard_data.year = year;
-                let _ = self.state.leaderboard.set(leaderboard_data);
+                let mut round = self.state.leaderboard_round.get().clone();
+                round.week = week;
+                round.year = year;
+                self.state.leaderboard_round.set(round);
                 
                 self.runtime.prepare_message(
                     Message::NewWeekStarted { week: week.clone(), year: year.clone(), prize_pool: prize_pool.clone() }
@@ -267,30 +309,33 @@ impl Contract for ManagementContract {
                 let management_chain_id = self.runtime.application_creator_chain_id();
 
                 //copy oracle chain and set new week
-                let mut leaderboard_data = self.state.leaderboard.get().clone();
-                leaderboard_data.week = 0;
-                leaderboard_data.year = 0;
-                let _ = self.state.leaderboard.set(leaderboard_data);
+                let mut round = self.state.leaderboard_round.get().clone();
+                round.week = 0;
+                round.year = 0;
+                self.state.leaderboard_round.set(round);
 
                 self.runtime.prepare_message(
                     Message::CurrentWeekEnded { week: week.clone(), year: year.clone() }
                 ).with_authentication().send_to(management_chain_id);
             },
             // UserChain operations.
-            Operation::PlaceBet { home_id, away_id, home_name, away_name, league, start_time, odd, selection, bid, event_id } => {
+            Operation::PlaceBet { home_id, away_id, home_name, away_name, league, start_time, min_acceptable_odd, selection, bid, event_id } => {
                 let management_chain_id = self.runtime.application_creator_chain_id();
-                
-                let user_balance = self.state.user_balance.get().clone();
 
-                if bid > user_balance {
-                    panic!("No tokens enough");
-                }
+                // Chains subscribed to the management chain know about suspensions, the management chain refunds the rest
+                let suspended = self.state.suspended_markets.contains(&event_id).await.expect("Failed to read suspended markets");
+                assert!(!suspended, "Market is suspended");
 
-                let new_balance = user_balance.saturating_sub(bid);
-                self.state.user_balance.set(new_balance);
+                // Allocate a bet id unique to this user chain
+                let bet_id = *self.state.nonce.get();
+                self.state.nonce.set(bet_id + 1);
 
-                // Record bet locally
+                // Escrow the stake on the management chain
+                self.escrow(bid, LedgerReason::BetStake, bet_id.to_string());
+
+                // Record bet locally, the odd is confirmed by the management chain
                 let user_bet = UserOdds {
+                    bet_id,
                     event_id: event_id.clone(),
                     teams: Teams { 
                         home: Team { 
@@ -304,37 +349,24 @@ impl Contract for ManagementContract {
                     },
                     league: league.clone(),
                     start_time,
-                    odd,
-                    selection: match selection.as_str() {
-                        "Home" => Selection::Home,
-                        "Away" => Selection::Away,
-                        "Tie" => Selection::Tie,
-                        _ => Selection::Home,
-                    },
+                    odd: min_acceptable_odd,
+                    selection,
                     bid: bid,
                     status: BetStatus::Placed,
                     placed_at: self.runtime.system_time(),
                 };
 
-                let mut user_odds_vec = self.state.user_odds.get().clone();
-                user_odds_vec.push(user_bet);
-                self.state.user_odds.set(user_odds_vec);
+                let _ = self.state.user_bets.insert(&bet_id, user_bet);
+                let _ = self.state.open_bets.insert(&bet_id);
 
                 // Notify management chain
                 self.runtime.prepare_message(
-                    Message::NewBetPlaced { home: home_id, away: away_id, league, start_time, odd, selection, bid, status: "Placed".to_string(), event_id  }
-                ).with_authentication().send_to(management_chain_id);
+                    Message::NewBetPlaced { bet_id, home: home_id, away: away_id, league, start_time, min_acceptable_odd, selection, bid, status: BetStatus::Placed, event_id  }
+                ).with_authentication().with_tracking().send_to(management_chain_id);
             },
             Operation::CreatePrediction { prediction_id, event_id, prediction_type, question, init_vote, amount } => {
                 let management_chain_id = self.runtime.application_creator_chain_id();
-                let user_balance = self.state.user_balance.get().clone();
-
-                if amount > user_balance {
-                    panic!("No tokens enough");
-                }
-
-                let new_balance = user_balance.saturating_sub(amount);
-                self.state.user_balance.set(new_balance);
+                self.escrow(amount, LedgerReason::PredictionStake, prediction_id.to_string());
                 //let prediction_type = PredictionType::from_str(prediction_type.as_str()).unwrap();
                 let userVotes = UserVotes {
                     id: prediction_id.clone(),
@@ -345,30 +377,21 @@ impl Contract for ManagementContract {
                     claimed: false,
                 };
                 
-                let mut user_votes_vec = self.state.user_votes.get().clone();
-                user_votes_vec.push(userVotes);
-                self.state.user_votes.set(user_votes_vec);
+                let _ = self.state.user_predictions.insert(&prediction_id, userVotes);
                 
                 self.runtime.prepare_message(
                     Message::NewPredictionCreated { prediction_id: prediction_id.clone(), event_id: event_id.clone(), prediction_type: prediction_type.clone(), question: question.clone(), init_vote: init_vote.clone(), amount: amount.clone() }
-                ).with_authentication().send_to(management_chain_id);
+                ).with_authentication().with_tracking().send_to(management_chain_id);
             },
             Operation::PlaceVote{ event_id, prediction_id, vote, amount, prediction_type } => {
                 let chain_id = self.runtime.application_creator_chain_id();
-                let user_balance = self.state.user_balance.get().clone();
-
-                if amount > user_balance {
-                    panic!("No tokens enough");
-                }
 
                 // Check for duplicate vote
-                let current_votes = self.state.user_votes.get();
-                if current_votes.iter().any(|v| v.id == prediction_id) {
+                if self.state.user_predictions.contains_key(&prediction_id).await.expect("Failed to read user votes") {
                     panic!("User has already voted on this prediction");
                 }
 
-                let new_balance = user_balance.saturating_sub(amount);
-                self.state.user_balance.set(new_balance);
+                self.escrow(amount, LedgerReason::PredictionStake, prediction_id.to_string());
 
                 //let prediction_type_enum = PredictionType::from_str(&prediction_type).unwrap();
 
@@ -381,56 +404,70 @@ impl Contract for ManagementContract {
                     claimed: false,
                 };
 
-                let mut user_votes_vec = current_votes.clone();
-                user_votes_vec.push(user_vote);
-                self.state.user_votes.set(user_votes_vec);
+                let _ = self.state.user_predictions.insert(&prediction_id, user_vote);
                 
                 self.runtime.prepare_message(
                     Message::NewVotePlaced { event_id: event_id.clone(), prediction_id: prediction_id.clone(), vote: vote.clone(), amount: amount.clone() }
-                ).with_authentication().send_to(chain_id);
+                ).with_authentication().with_tracking().send_to(chain_id);
             },  
-            Operation::ClaimReward{ event_id } => {
+            Operation::ClaimReward{ bet_id } => {
                 let chain_id = self.runtime.application_creator_chain_id();
+                let user_bet = self.state.user_bets.get(&bet_id).await
+                    .expect("Failed to read bet")
+                    .expect("Bet not found");
+                assert_eq!(user_bet.status, BetStatus::Placed, "Bet has already been settled");
+                let event_id = user_bet.event_id;
                 self.runtime.prepare_message(
-                    Message::UserClaimReward { event_id: event_id.clone() }
+                    Message::UserClaimReward { event_id, bet_id }
                 ).with_authentication().send_to(chain_id);
             },
             Operation::RequestMint{ amount } => {
-                let chain_id = self.runtime.application_creator_chain_id();
                 let bonus_claimed = self.state.bonus_claimed.get().clone();
                 if bonus_claimed {
                     return;
                 }
                 self.state.bonus_claimed.set(true);
+
+                // The welcome bonus is paid from the house funds on the management chain
+                let chain_id = self.runtime.application_creator_chain_id();
                 self.runtime.prepare_message(
-                    Message::MintTokens { amount: amount.clone() }
-                ).with_authentication().send_to(chain_id);  
+                    Message::ClaimWelcomeBonus { amount }
+                ).with_authentication().send_to(chain_id);
             },
             Operation::ClaimPredictionReward{ prediction_id, event_id } => {
                 let chain_id = self.runtime.application_creator_chain_id();
                 //get in local chain with the prediction and event and get the user votes
                 //iterate over the user votes and check if the user has already claimed the reward
-                let mut user_votes = self.state.user_votes.get().clone();
-                for user_vote in user_votes.iter_mut() {
-                    if user_vote.id == prediction_id && user_vote.event_id == event_id && !user_vote.claimed {
+                if let Some(mut user_vote) = self.state.user_predictions.get(&prediction_id).await.expect("Failed to read user votes") {
+                    if user_vote.event_id == event_id && !user_vote.claimed {
                         //update the user vote
                         user_vote.claimed = true;
+                        let _ = self.state.user_predictions.insert(&prediction_id, user_vote);
                         self.runtime.prepare_message(
                             Message::SendPredictionReward { prediction_id: prediction_id.clone(), event_id: event_id.clone() }
                         ).with_authentication().send_to(chain_id);  
-                    }else{
-                        //do nothing
                     }
                 }
-                self.state.user_votes.set(user_votes);
             },
         }
     }
 
     async fn execute_message(&mut self, message: Self::Message) {
+        if self.runtime.message_is_bouncing() == Some(true) {
+            self.handle_bounced_message(message).await;
+            return;
+        }
+
+        if self.runtime.chain_id() == self.runtime.application_creator_chain_id() {
+            self.record_user_owner().await;
+            self.void_expired_postponed_events().await;
+            self.resume_goal_suspensions().await;
+            self.release_pending_bets().await;
+        }
+
         match message {
             Message::UpdateTeamPower { team_id, name, power, form, goal_average } => {
-                let management_chain_id = self.runtime.application_creator_chain_id();
+                self.assert_oracle_origin();
                 let mut team = self.state.power_ranking.get(&team_id).await.expect("Team not found").unwrap_or_default();
                 team.id = team_id.clone();
                 team.name = name;
@@ -440,156 +477,122 @@ impl Contract for ManagementContract {
                 team.last_updated = self.runtime.system_time();
                 let _ = self.state.power_ranking.insert(&team_id.clone(), team.clone());
             },
-            Message::NewBetPlaced { home, away, league, start_time, odd, selection, bid, status, event_id } => {
+            Message::NewBetPlaced { bet_id, home, away, league, start_time, min_acceptable_odd, selection, bid, status, event_id } => {
                 let user_id = self.runtime.message_origin_chain_id().unwrap();
-                
+                let signer = self.runtime.authenticated_signer();
+                // The stake reached the handler, it can no longer be refunded as bounced
+                let _ = self.take_escrow_receipt(user_id, LedgerReason::BetStake, bet_id.to_string()).await;
+                let bet = PendingBet {
+                    bet_id,
+                    user_id,
+                    owner: signer,
+                    selection,
+                    bid,
+                    min_acceptable_odd,
+                    received_at: self.runtime.system_time(),
+                };
+
                 // Check if event exists, if not revert the bet
-                let mut event = match self.state.events.get(&event_id).await {
+                let event = match self.state.events.get(&event_id).await {
                     Ok(Some(e)) => e,
                     _ => {
-                        self.runtime.prepare_message(
-                            Message::RevertUserBet { event_id: event_id.clone() }
-                        ).with_authentication().send_to(user_id);
+                        self.revert_bet(user_id, signer, bet_id, bid).await;
                         return;
                     }
                 };
-                
-                if event.status != MatchStatus::Scheduled {
-                    self.runtime.prepare_message(
-                        Message::RevertUserBet { event_id: event_id.clone() }
-                    ).with_authentication().send_to(user_id);
-
-                    //send back the bid
-                    self.runtime.prepare_message(
-                        Message::Receive { amount: bid.clone() }
-                    ).with_authentication().send_to(user_id);
 
+                if !matches!(event.status, MatchStatus::Scheduled | MatchStatus::Live) || event.suspended {
+                    self.revert_bet(user_id, signer, bet_id, bid).await;
                     return;
                 }
 
-                // Update event pool
-                //event.pool = event.pool.saturating_add(bid);
-                //self.state.events.insert(&event_id, event);
-
-                // Record bet
-                let mut bets = self.state.event_odds.get(&event_id).await.expect("Event not found").unwrap_or_default();
-                let bet = UserOdd {
-                    user_id: user_id.to_string(),
-                    odd,
-                    selection: match selection.as_str() {
-                        "Home" => Selection::Home,
-                        "Away" => Selection::Away,
-                        "Tie" => Selection::Tie,
-                        _ => Selection::Home,
-                    },
-                    placed_at: self.runtime.system_time(),
-                    bid,
-                };
-
-                bets.push(bet.clone());
-                let _ = self.state.event_odds.insert(&event_id, bets.clone());
-                self.runtime.emit(STREAM_NAME.into(), &Bet::NewEventBet { event_id: event_id.clone(), user_odd: bet });
-
-                //calculate new odds
-                let initial_odds = (event.odds.home, event.odds.tie, event.odds.away);
-                let (new_home, new_tie, new_away) = get_market_odds(initial_odds, &bets);
-
-                event.odds.home = new_home;
-                event.odds.tie = new_tie;
-                event.odds.away = new_away;
-                let _ = self.state.events.insert(&event_id, event);
-
-                let mut leaderboard_data = self.state.leaderboard
-                    .get().clone();
-
-                let user_stats = leaderboard_data.user_stats.get(&user_id.to_string()).cloned().unwrap_or_default();
-                let user_stats = UserStats {
-                    total_staked: user_stats.total_staked.saturating_add(bid),
-                    total_winnings: user_stats.total_winnings,
-                    total_bets: user_stats.total_bets.saturating_add(1),
-                    total_wins: user_stats.total_wins,
-                    total_losses: user_stats.total_losses,
-                    win_rate: user_stats.win_rate,
-                };
-                let _ = leaderboard_data.user_stats.insert(user_id.to_string(), user_stats);
-                let _ = self.state.leaderboard.set(leaderboard_data);
+                // In-play bets wait out the bet delay, they are only taken if nothing happens meanwhile
+                if event.status == MatchStatus::Live && self.runtime.application_parameters().bet_delay > 0 {
+                    let mut pending = self.state.pending_bets.get(&event_id).await.expect("Failed to read pending bets").unwrap_or_default();
+                    pending.push(bet);
+                    let _ = self.state.pending_bets.insert(&event_id, pending);
+                    return;
+                }
+                self.accept_bet(event_id, event, bet).await;
             },
             Message::NewVotePlaced { event_id, prediction_id, vote, amount } => {
                 let user_id = self.runtime.message_origin_chain_id().unwrap();
-                let mut event = match self.state.events.get(&event_id).await {
+                let signer = self.runtime.authenticated_signer();
+                let _ = self.take_escrow_receipt(user_id, LedgerReason::PredictionStake, prediction_id.to_string()).await;
+                let event = match self.state.events.get(&event_id).await {
                     Ok(Some(e)) => e,
                     _ => {
-                        self.runtime.prepare_message(
-                            Message::Receive { amount: amount.clone() }
-                        ).with_authentication().send_to(user_id);
+                        self.pay(user_id, signer, amount, LedgerReason::PredictionRefund, prediction_id.to_string()).await;
                         return;
                     }
                 };
 
                 if event.status != MatchStatus::Live {
-                    self.runtime.prepare_message(
-                        Message::Receive { amount: amount.clone() }
-                    ).with_authentication().send_to(user_id);
+                    self.pay(user_id, signer, amount, LedgerReason::PredictionRefund, prediction_id.to_string()).await;
                     return;
                 }
 
-                if let Some(prediction) = event.predictions.iter_mut().find(|p| p.id == prediction_id) {
-                    if prediction.resolved {
-                        self.runtime.prepare_message(
-                            Message::Receive { amount: amount.clone() }
-                        ).with_authentication().send_to(user_id);
-                        return;
-                    }
-
-                    // Check if user already voted
-                    if prediction.votes.iter().any(|v| v.user == user_id.to_string()) {
-                        self.runtime.prepare_message(
-                            Message::Receive { amount: amount.clone() }
-                        ).with_authentication().send_to(user_id);
-                        return; 
-                    }
-
-                    // Update pools
-                    if vote {
-                        prediction.pool_yes = prediction.pool_yes.saturating_add(amount);
-                    } else {
-                        prediction.pool_no = prediction.pool_no.saturating_add(amount);
-                    }
+                let key = (event_id.clone(), prediction_id);
+                let Some(mut prediction) = self.state.live_predictions.get(&key).await.expect("Failed to read prediction") else {
+                    // Prediction not found
+                    self.pay(user_id, signer, amount, LedgerReason::PredictionRefund, prediction_id.to_string()).await;
+                    return;
+                };
+                if prediction.resolved {
+                    self.pay(user_id, signer, amount, LedgerReason::PredictionRefund, prediction_id.to_string()).await;
+                    return;
+                }
 
-                    let new_vote = Vote{
-                        user: user_id.to_string(),
-                        choice: vote.clone(),
-                        amount: amount.clone(),
-                        claimed: false,
-                    };
+                // Check if user already voted
+                let already_voted = self.state.prediction_votes.load_entry_mut(&key).await
+                    .expect("Failed to read prediction votes")
+                    .contains_key(&user_id.to_string()).await
+                    .expect("Failed to read prediction votes");
+                if already_voted {
+                    self.pay(user_id, signer, amount, LedgerReason::PredictionRefund, prediction_id.to_string()).await;
+                    return; 
+                }
 
-                    prediction.votes.push(new_vote);
+                // Update pools
+                if vote {
+                    prediction.pool_yes = prediction.pool_yes.saturating_add(amount);
                 } else {
-                    // Prediction not found
-                    self.runtime.prepare_message(
-                        Message::Receive { amount: amount.clone() }
-                    ).with_authentication().send_to(user_id);
-                    return;
+                    prediction.pool_no = prediction.pool_no.saturating_add(amount);
                 }
 
-                let _ =  self.state.events.insert(&event_id, event);
+                let new_vote = Vote{
+                    user: user_id.to_string(),
+                    choice: vote.clone(),
+                    amount: amount.clone(),
+                    claimed: false,
+                    owner: signer,
+                };
+
+                let _ = self.state.prediction_votes.load_entry_mut(&key).await
+                    .expect("Failed to read prediction votes")
+                    .insert(&user_id.to_string(), new_vote);
+                let _ = self.state.live_predictions.insert(&key, prediction);
             },
             Message::NewPredictionCreated { prediction_id, event_id, prediction_type, question, init_vote, amount } => {
                 let user_id = self.runtime.message_origin_chain_id().unwrap();
-                let mut event = match self.state.events.get(&event_id).await {
+                let signer = self.runtime.authenticated_signer();
+                let _ = self.take_escrow_receipt(user_id, LedgerReason::PredictionStake, prediction_id.to_string()).await;
+                let event = match self.state.events.get(&event_id).await {
                     Ok(Some(e)) => e,
                     _ => {
-                        self.runtime.prepare_message(
-                            Message::Receive { amount: amount.clone() }
-                        ).with_authentication().send_to(user_id);
+                        self.pay(user_id, signer, amount, LedgerReason::PredictionRefund, prediction_id.to_string()).await;
                         return;
                     }
                 };
 
                 if event.status != MatchStatus::Live {
-                    self.runtime.prepare_message(
-                        Message::Receive { amount: amount.clone() }
-                    ).with_authentication().send_to(user_id);
+                    self.pay(user_id, signer, amount, LedgerReason::PredictionRefund, prediction_id.to_string()).await;
+                    return;
+                }
+
+                let key = (event_id.clone(), prediction_id);
+                if self.state.live_predictions.contains_key(&key).await.expect("Failed to read prediction") {
+                    self.pay(user_id, signer, amount, LedgerReason::PredictionRefund, prediction_id.to_string()).await;
                     return;
                 }
 
@@ -598,6 +601,7 @@ impl Contract for ManagementContract {
                     choice: init_vote.clone(),
                     amount: amount.clone(),
                     claimed: false,
+                    owner: signer,
                 };
 
 
@@ -611,24 +615,32 @@ impl Contract for ManagementContract {
                     resolved: false,         
                     outcome: None,  
                     created_at: self.runtime.system_time(),
-                    votes: vec![new_vote]
+                    votes: Vec::new()
                 };
 
-                event.predictions.push(new_prediction);
-                let _ =  self.state.events.insert(&event_id, event);
+                let mut ids = self.state.event_prediction_ids.get(&event_id).await.expect("Failed to read event predictions").unwrap_or_default();
+                ids.push(prediction_id);
+                let _ = self.state.event_prediction_ids.insert(&event_id, ids);
+                let _ = self.state.prediction_votes.load_entry_mut(&key).await
+                    .expect("Failed to read prediction votes")
+                    .insert(&user_id.to_string(), new_vote);
+                let _ = self.state.live_predictions.insert(&key, new_prediction);
             },
-            Message::RevertUserBet { event_id } => {
-                let mut user_odds_vec = self.state.user_odds.get().clone(); 
-                for user_odd in &mut user_odds_vec {
-                    if user_odd.event_id == event_id {
-                        user_odd.status = BetStatus::Cancelled;
-                    }
+            Message::BetAccepted { bet_id, odd, bid } => {
+                if let Some(mut user_odd) = self.state.user_bets.get(&bet_id).await.expect("Failed to read bet") {
+                    user_odd.odd = odd;
+                    user_odd.bid = bid;
+                    let _ = self.state.user_bets.insert(&bet_id, user_odd);
                 }
-
-                let _ = self.state.user_odds.set(user_odds_vec);
+            },
+            Message::RevertUserBet { bet_id } => {
+                self.set_user_bet_status(bet_id, BetStatus::Cancelled).await;
+            },
+            Message::VoteRefunded { prediction_id } => {
+                self.mark_vote_refunded(prediction_id).await;
             },
 
-            Message::UserClaimReward { event_id } => {
+            Message::UserClaimReward { event_id, bet_id } => {
                 let user_id = self.runtime.message_origin_chain_id().unwrap();
                 
                 // Check if event exists
@@ -636,119 +648,103 @@ impl Contract for ManagementContract {
                     Ok(Some(e)) => e,
                     _ => {
                         self.runtime.prepare_message(
-                            Message::ClaimResult { event_id: event_id.clone(), result: "Cancelled".to_string() }
+                            Message::ClaimResult { bet_id, result: BetStatus::Cancelled }
                         ).with_authentication().send_to(user_id);
                         return;
                     }
                 };
                 
-                if event.status != MatchStatus::Finished {
+                if event.status != MatchStatus::Finished && !event.status.is_void() {
                     self.runtime.prepare_message(
-                        Message::ClaimResult { event_id: event_id.clone(), result: "Placed".to_string() }
+                        Message::ClaimResult { bet_id, result: BetStatus::Placed }
                     ).with_authentication().send_to(user_id);
                     return; 
                 }
 
-                let bets = self.state.event_odds.get(&event_id).await.expect("Event not found").unwrap_or_default();
-    
-                for bet in bets.clone() {
-                    if bet.user_id == user_id.to_string() {
-                        if bet.selection == event.result.winner {
-                            // Calculate prize
-                            let prize = calculate_prize(&event, &bet);
-                            
-                            self.runtime.prepare_message(
-                                Message::Receive { amount: prize }
-                            ).with_authentication().send_to(user_id);
-
-                            self.runtime.prepare_message(
-                                Message::ClaimResult { event_id: event_id.clone(), result: "Won".to_string()  }
-                            ).with_authentication().send_to(user_id);
-
-                            let mut leaderboard_data = self.state.leaderboard
-                                .get().clone();
-
-                            let user_stats = leaderboard_data.user_stats.get(&user_id.to_string()).cloned().unwrap_or_default();
-                            let win_rate = (user_stats.total_wins.saturating_add(1) as f64 / user_stats.total_bets as f64 * 100.0) as u64;
-                            let user_stats = UserStats {
-                                total_staked: user_stats.total_staked,
-                                total_winnings: user_stats.total_winnings.saturating_add(prize),
-                                total_bets: user_stats.total_bets,
-                                total_wins: user_stats.total_wins.saturating_add(1),
-                                total_losses: user_stats.total_losses,
-                                win_rate: win_rate,
-                            };
-                            let _ = leaderboard_data.user_stats.insert(user_id.to_string(), user_stats);
-                            let _ = self.state.leaderboard.set(leaderboard_data);
-                        } else {
-                            self.runtime.prepare_message(
-                                Message::ClaimResult { event_id: event_id.clone(), result: "Lost".to_string() }
-                            ).with_authentication().send_to(user_id);
-
-                            let mut leaderboard_data = self.state.leaderboard
-                                .get().clone();
-
-                            let user_stats = leaderboard_data.user_stats.get(&user_id.to_string()).cloned().unwrap_or_default();
-                            let win_rate = (user_stats.total_wins as f64 / user_stats.total_bets as f64 * 100.0) as u64;
-                            let user_stats = UserStats {
-                                total_staked: user_stats.total_staked,
-                                total_winnings: user_stats.total_winnings,
-                                total_bets: user_stats.total_bets,
-                                total_wins: user_stats.total_wins,
-                                total_losses: user_stats.total_losses.saturating_add(1),
-                                win_rate: win_rate,
-                            };
-                            let _ = leaderboard_data.user_stats.insert(user_id.to_string(), user_stats);
-                            let _ = self.state.leaderboard.set(leaderboard_data);
-                        }
-                    }
-                }
-            },
+                let position = self.state.bet_positions.get(&(event_id.clone(), user_id.to_string(), bet_id)).await.expect("Failed to read bet position");
+                let bet = match position {
+                    Some(position) => self.state.event_bets.get(&(event_id.clone(), position)).await.expect("Failed to read bet"),
+                    None => None,
+                };
+                let (Some(position), Some(mut bet)) = (position, bet) else {
+                    self.runtime.prepare_message(
+                        Message::ClaimResult { bet_id, result: BetStatus::Cancelled }
+                    ).with_authentication().send_to(user_id);
+                    return;
+                };
 
-            Message::ClaimResult { event_id, result } => {
-                let mut user_odds_vec = self.state.user_odds.get().clone();
-                let res = match result.as_str() {
-                        "Won" => BetStatus::Won,
-                        "Lost" => BetStatus::Lost,
-                        "Cancelled" => BetStatus::Cancelled,
-                        _ => BetStatus::Placed,
-                    };
-                for bet in &mut user_odds_vec {
-                    if bet.event_id == event_id {
-                        bet.status = res;
-                    }
+                // Already settled: only resync the user chain, never pay twice
+                if bet.status != BetStatus::Placed {
+                    self.runtime.prepare_message(
+                        Message::ClaimResult { bet_id, result: bet.status }
+                    ).with_authentication().send_to(user_id);
+                    return;
                 }
-                let _ = self.state.user_odds.set(user_odds_vec);
+
+                self.settle_bet(user_id, &event, &mut bet).await;
+                let _ = self.state.event_bets.insert(&(event_id, position), bet);
             },
-            Message::Receive { amount } => {
-                let current_balance = *self.state.user_balance.get();
 
-                let new_balance = current_balance.saturating_add(amount.into());
-                self.state.user_balance.set(new_balance);
+            Message::ClaimResult { bet_id, result } => {
+                self.set_user_bet_status(bet_id, result).await;
             },
-            Message::MintTokens { amount } => {
+            Message::StakeEscrowed { reason, reference, amount } => {
                 let user_chain_id = self.runtime.message_origin_chain_id().unwrap();
-                let current_supply = self.state.token_supp.get().clone();
-                let new_supply = current_supply.saturating_add(amount.into());
-
-                self.state.token_supp.set(new_supply);  
-
-                self.runtime.prepare_message(
-                    Message::Receive { amount: amount.clone() }
-                ).with_authentication().send_to(user_chain_id);
+                let _ = self.state.escrow_receipts.insert(&(user_chain_id.to_string(), reason, reference), amount);
+            },
+            Message::RefundBouncedStake { reason, reference } => {
+                // Only a stake still in the escrow is refunded, and only once
+                let user_chain_id = self.runtime.message_origin_chain_id().unwrap();
+                let Some(amount) = self.take_escrow_receipt(user_chain_id, reason, reference.clone()).await else {
+                    return;
+                };
+                self.pay(user_chain_id, None, amount, LedgerReason::BouncedStakeRefund, reference).await;
+            },
+            Message::ClaimWelcomeBonus { amount } => {
+                let user_chain_id = self.runtime.message_origin_chain_id().unwrap();
+                let amount = amount.min(self.runtime.application_parameters().welcome_bonus);
+                self.pay(user_chain_id, self.runtime.authenticated_signer(), amount, LedgerReason::WelcomeBonus, String::new()).await;
+            },
+            Message::Credited { amount, reason, reference } => {
+                let origin = self.runtime.message_origin_chain_id().unwrap();
+                self.record_transaction(LedgerDirection::Credit, amount, reason, reference, origin);
             },
             Message::NewEventCreated { event_id, event } =>{
+                self.assert_oracle_origin();
+                self.record_odds(&event_id, &event.odds, OddsChangeReason::Opening, None).await;
                 let _ = self.state.events.insert(&event_id.clone(), event.clone());
             },
             Message::EventStatusUpdated { event_id, status } => {
+                self.assert_oracle_origin();
+                if status.is_void() {
+                    self.void_event(event_id, status).await;
+                    return;
+                }
                 if let Some(mut event) = self.state.events.get(&event_id).await.expect("Event not found") {
                     event.status = status;
                     event.last_updated = self.runtime.system_time();
+                    self.reprice_live(&event_id, &mut event).await;
                     let _ = self.state.events.insert(&event_id, event);
                 }
+                // Track how long an event stays postponed, a new status means it was rescheduled
+                if status == MatchStatus::Postponed {
+                    let now = self.runtime.system_time();
+                    let _ = self.state.postponed_events.insert(&event_id, now);
+                } else {
+                    let _ = self.state.postponed_events.remove(&event_id);
+                }
+            },
+            Message::EventVoided { event_id, status } => {
+                self.assert_oracle_origin();
+                self.void_event(event_id, status).await;
             },
             Message::EventScoreUpdated { event_id, home_score, away_score } => {
+                self.assert_oracle_origin();
                 if let Some(mut event) = self.state.events.get(&event_id).await.expect("Event not found") {
+                    // The oracle repeats the score on every poll, only a change holds in-play bets back
+                    if (event.live_score.home, event.live_score.away) != (home_score, away_score) {
+                        let _ = self.state.last_incidents.insert(&event_id, self.runtime.system_time());
+                    }
                      let live_score = LiveScore {
                         home: home_score,
                         away: away_score,
@@ -756,26 +752,65 @@ impl Contract for ManagementContract {
                     };
                     event.live_score = live_score;
                     event.last_updated = self.runtime.system_time();
+                    self.reprice_live(&event_id, &mut event).await;
+                    let _ = self.state.events.insert(&event_id, event);
+                }
+            },
+            Message::EventOddsUpdated { event_id, home, away, tie } => {
+                self.assert_oracle_origin();
+                if let Some(mut event) = self.state.events.get(&event_id).await.expect("Event not found") {
+                    let params = self.runtime.application_parameters();
+                    // The override becomes the base the market is priced from, pool and exposure still apply
+                    let odds = Odds {
+                        home: home.clamp(params.min_odd, params.max_odd),
+                        away: away.clamp(params.min_odd, params.max_odd),
+                        tie: tie.clamp(params.min_odd, params.max_odd),
+                    };
+                    let state = if event.status == MatchStatus::Live { event.match_state() } else { MatchState::default() };
+                    let _ = self.state.pricing_bases.insert(&event_id, PricingBase { odds, state });
+
+                    let stakes = self.state.event_stakes.get(&event_id).await.expect("Failed to read event stakes").unwrap_or_default();
+                    let liabilities = self.state.event_liabilities.get(&event_id).await.expect("Failed to read event liabilities").unwrap_or_default();
+                    event.odds = self.market_odds(&event_id, &event, &stakes, &liabilities).await;
+                    event.last_updated = self.runtime.system_time();
+                    self.record_odds(&event_id, &event.odds, OddsChangeReason::OracleAdjust, None).await;
+                    let _ = self.state.events.insert(&event_id, event);
+                }
+            },
+            Message::MarketSuspensionChanged { event_id, suspended } => {
+                self.assert_oracle_origin();
+                if let Some(mut event) = self.state.events.get(&event_id).await.expect("Event not found") {
+                    event.suspended = suspended;
+                    event.last_updated = self.runtime.system_time();
                     let _ = self.state.events.insert(&event_id, event);
+                    self.runtime.emit(STREAM_NAME.into(), &Bet::MarketStatusChanged { event_id: event_id.clone(), suspended });
                 }
+                // The oracle took over, a goal suspension no longer resumes on its own
+                let _ = self.state.goal_suspensions.remove(&event_id);
             },
             Message::EventMinuteUpdated { event_id, minute } => {
+                self.assert_oracle_origin();
                 if let Some(mut event) = self.state.events.get(&event_id).await.expect("Event not found") {
                     event.current_minute = Some(minute);
                     event.last_updated = self.runtime.system_time();
+                    self.reprice_live(&event_id, &mut event).await;
                     let _ = self.state.events.insert(&event_id, event);
                 }
             },
             Message::EventMatchEventAdded { event_id, match_event } => {
+                self.assert_oracle_origin();
                  if let Some(mut event) = self.state.events.get(&event_id).await.expect("Event not found") {
                      event.match_events.push(match_event.clone());
+                     if matches!(match_event.event_type, MatchEventType::Goal | MatchEventType::RedCard) {
+                         let _ = self.state.last_incidents.insert(&event_id, self.runtime.system_time());
+                     }
                      
                      // Auto-resolve predictions
                      let mut resolved_messages = Vec::new();
                      let match_event_type = match_event.event_type;
                      let team = match_event.team;
 
-                     for prediction in event.predictions.iter_mut().filter(|p| !p.resolved) {
+                     for mut prediction in self.open_predictions(&event_id).await {
                          let mut is_resolved = false;
                          let mut result = false;
 
@@ -843,29 +878,37 @@ impl Contract for ManagementContract {
                              prediction.resolved = true;
                              prediction.outcome = Some(result);
                              resolved_messages.push((prediction.id, result));
+                             let _ = self.state.live_predictions.insert(&(event_id.clone(), prediction.id), prediction);
                          }
                      }
+
+                     // Goals close the in-play market for the bet delay
+                     if match_event_type == MatchEventType::Goal && event.status == MatchStatus::Live && !event.suspended {
+                         event.suspended = true;
+                         let _ = self.state.goal_suspensions.insert(&event_id, self.runtime.system_time());
+                         self.runtime.emit(STREAM_NAME.into(), &Bet::MarketStatusChanged { event_id: event_id.clone(), suspended: true });
+                     }
+                     self.reprice_live(&event_id, &mut event).await;
                      
                      let _ = self.state.events.insert(&event_id, event);
                  }
             },
             Message::EventOutcomeResolved { event_id, winner, home_score, away_score } => {
+                self.assert_oracle_origin();
                 if let Some(mut event) = self.state.events.get(&event_id).await.expect("Event not found") {
                     let result = MatchResult {
                         winner,
-                        home_score: home_score.clone(),
-                        away_score: away_score.clone(),
+                        home_score,
+                        away_score,
                     };
 
                     event.result = result;
                     event.status = MatchStatus::Finished;
 
                     // Resolve TotalGoalsUnder predictions
-                    let h_score = home_score.parse::<u8>().unwrap_or(0);
-                    let a_score = away_score.parse::<u8>().unwrap_or(0);
-                    let total_goals = h_score + a_score;
+                    let total_goals = home_score.saturating_add(away_score);
 
-                    for prediction in event.predictions.iter_mut().filter(|p| !p.resolved) {
+                    for mut prediction in self.open_predictions(&event_id).await {
                         let mut resolved = false;
                         let mut outcome = false;
 
@@ -881,7 +924,7 @@ impl Contract for ManagementContract {
                              },
                              PredictionType::BTTS => {
                                   // Both teams must have scored at least 1 goal
-                                  outcome = h_score > 0 && a_score > 0;
+                                  outcome = home_score > 0 && away_score > 0;
                                   resolved = true;
                              },
                              PredictionType::NextGoal(_) => {
@@ -904,94 +947,103 @@ impl Contract for ManagementContract {
                         if resolved {
                              prediction.resolved = true;
                              prediction.outcome = Some(outcome);
+                             let _ = self.state.live_predictions.insert(&(event_id.clone(), prediction.id), prediction);
                         }
                     }
 
                     
                     let _ = self.state.events.insert(&event_id, event);
+
+                    if self.runtime.application_parameters().auto_settle {
+                        let _ = self.state.settlement_cursors.insert(&event_id, 0);
+                        self.settle_event_batch(event_id).await;
+                    }
                 }
             },
+            Message::SettleEventBatch { event_id } => {
+                assert_eq!(
+                    self.runtime.message_origin_chain_id(),
+                    Some(self.runtime.chain_id()),
+                    "Settlement batches can only be scheduled by the management chain"
+                );
+                self.settle_event_batch(event_id).await;
+            },
 
             Message::SendPredictionReward { prediction_id, event_id } => {
                 let user_id = self.runtime.message_origin_chain_id().unwrap();
-                //get the event with his predictions
-                let mut event = self.state.events.get(&event_id.clone()).await.expect("Event not found").unwrap();
+                assert!(self.state.events.contains_key(&event_id).await.expect("Failed to read event"), "Event not found");
+                let key = (event_id.clone(), prediction_id);
                 
-                //iterate over predictions vec to find the prediction with prediction_id
-                if let Some(prediction) = event.predictions.iter_mut().find(|p| p.id == prediction_id) {
+                //find the prediction with prediction_id
+                if let Some(prediction) = self.state.live_predictions.get(&key).await.expect("Failed to read prediction") {
                     if prediction.resolved && prediction.outcome.is_some() {
                         let result = prediction.outcome.unwrap();
                         
-                        //iterate over votes vec to find the vote with user_id
-                        for vote in prediction.votes.iter_mut() {
-                             if vote.user == user_id.to_string() {
-                                if !vote.claimed {
-                                    // Verify if user won
-                                    if vote.choice == result {
-                                        let user_vote_amount = vote.amount;
-                                        
-                                        // Calculate Reward: (UserStake * TotalPool) / WinningPool
-                                        let total_pool = prediction.pool_yes.saturating_add(prediction.pool_no);
-                                        let winning_pool = if result { prediction.pool_yes } else { prediction.pool_no };
-
-                                        // Safety check for div by zero (shouldn't happen if user voted, but good practice)
-                                        if winning_pool > Amount::ZERO {
-                                            // arithmetic logic for rewards must be done in u128
-                                            let user_vote_u128: u128 = user_vote_amount.into();
-                                            let total_pool_u128: u128 = total_pool.into();
-                                            let winning_pool_u128: u128 = winning_pool.into();
-
-                                            let reward_u128 = (user_vote_u128 * total_pool_u128) / winning_pool_u128;
-                                            let reward = Amount::from_attos(reward_u128);
-
-                                            //mark the vote as claimed
-                                            vote.claimed = true;
-
-                                            //send the reward to the user
-                                            self.runtime.prepare_message(
-                                                Message::Receive { amount: reward }
-                                            ).with_authentication().send_to(user_id);
-                                        }
-                                    } else {
-                                        vote.claimed = true;
-                                    }
+                        //find the vote of user_id
+                        let user_vote = self.state.prediction_votes.load_entry_mut(&key).await
+                            .expect("Failed to read prediction votes")
+                            .get(&user_id.to_string()).await
+                            .expect("Failed to read prediction votes");
+                        if let Some(mut vote) = user_vote.filter(|v| !v.claimed) {
+                            // Verify if user won
+                            if vote.choice == result {
+                                let user_vote_amount = vote.amount;
+                                
+                                // Calculate Reward: (UserStake * TotalPool) / WinningPool
+                                let total_pool = prediction.pool_yes.saturating_add(prediction.pool_no);
+                                let winning_pool = if result { prediction.pool_yes } else { prediction.pool_no };
+
+                                // Safety check for div by zero (shouldn't happen if user voted, but good practice)
+                                if winning_pool > Amount::ZERO {
+                                    // arithmetic logic for rewards must be done in u128
+                                    let user_vote_u128: u128 = user_vote_amount.into();
+                                    let total_pool_u128: u128 = total_pool.into();
+                                    let winning_pool_u128: u128 = winning_pool.into();
+
+                                    let reward_u128 = (user_vote_u128 * total_pool_u128) / winning_pool_u128;
+                                    let reward = Amount::from_attos(reward_u128);
+
+                                    //mark the vote as claimed
+                                    vote.claimed = true;
+
+                                    //send the reward to the user
+                                    self.pay(user_id, vote.owner, reward, LedgerReason::PredictionReward, prediction_id.to_string()).await;
                                 }
-                             }
+                            } else {
+                                vote.claimed = true;
+                            }
+                            let _ = self.state.prediction_votes.load_entry_mut(&key).await
+                                .expect("Failed to read prediction votes")
+                                .insert(&user_id.to_string(), vote);
                         }
                     }
                 }
-                let _ = self.state.events.insert(&event_id, event);
             },  
             //leaderboard cross-messages
             Message::NewWeekStarted { week, year, prize_pool } => {
-                let mut leaderboard_data = self.state.leaderboard.get().clone();
-                leaderboard_data.week = week;
-                leaderboard_data.year = year;
-                leaderboard_data.user_stats = HashMap::new();
-                leaderboard_data.winners = leaderboard_data.winners.clone();
-                leaderboard_data.prize_pool = prize_pool;
-                let _ = self.state.leaderboard.set(leaderboard_data);
+                self.assert_oracle_origin();
+                let round = LeaderboardRound { week, year, prize_pool };
+                self.state.leaderboard_round.set(round);
+                self.state.user_stats.clear();
             },
             Message::CurrentWeekEnded { week, year } => {
-                let mut leaderboard_data = self.state.leaderboard.get().clone();
+                self.assert_oracle_origin();
+                let round = self.state.leaderboard_round.get().clone();
                 
                 // Calculate winners
                 let mut winners = Vec::new();
-                for (user_id, user_stats) in leaderboard_data.user_stats.iter() {
-                    winners.push((user_id.clone(), user_stats.total_winnings));
-                }
+                self.state.user_stats.for_each_index_value(|user_id, user_stats| {
+                    winners.push((user_id, user_stats.total_winnings));
+                    Ok(())
+                }).await.expect("Failed to read user stats");
                 winners.sort_by(|a, b| b.1.cmp(&a.1));
                 
-                // Create Vec of top 3 winners
+                // Create Vec of winners, one per configured prize share
+                let split = self.runtime.application_parameters().leaderboard_split;
                 let mut week_winners = Vec::new();
-                for (i, (user_id, _)) in winners.iter().take(3).enumerate() {
-                    let pool_amount: u128 = leaderboard_data.prize_pool.into();
-                    let prize = match i {
-                        0 => Amount::from_attos(pool_amount * 50 / 100),  // 1er: 50%
-                        1 => Amount::from_attos(pool_amount * 30 / 100),  // 2do: 30%
-                        2 => Amount::from_attos(pool_amount * 20 / 100),  // 3er: 20%
-                        _ => Amount::ZERO,
-                    };
+                for (i, (user_id, _)) in winners.iter().take(split.len()).enumerate() {
+                    let pool_amount: u128 = round.prize_pool.into();
+                    let prize = Amount::from_attos(pool_amount * split[i] as u128 / 100);
 
                     week_winners.push(LeaderboardWinner {
                         user: user_id.clone(),
@@ -1000,14 +1052,11 @@ impl Contract for ManagementContract {
                     });
 
                     let user_chain_id = ChainId::from_str(&user_id).unwrap();
-                    self.runtime.prepare_message(
-                        Message::Receive { amount: prize.clone() }
-                    ).with_authentication().send_to(user_chain_id);
+                    self.pay(user_chain_id, None, prize, LedgerReason::LeaderboardPrize, format!("{}-{}", year, week)).await;
                 }
                 
                 // Insert Vec of winners with "year-week" key
-                leaderboard_data.winners.insert(format!("{}-{}", year, week), week_winners);
-                let _ = self.state.leaderboard.set(leaderboard_data);
+                let _ = self.state.weekly_winners.insert(&format!("{}-{}", year, week), week_winners);
             }
          }
     }
@@ -1025,11 +1074,14 @@ impl Contract for ManagementContract {
                     .read_event(update.chain_id, STREAM_NAME.into(), index);
                 match event {
                     Bet::NewEventBet { event_id, user_odd } => {
-                        let mut odds = self.state.event_odds.get(&event_id).await
-                            .unwrap_or(None)
-                            .unwrap_or_default();
-                        odds.push(user_odd);
-                        let _ = self.state.event_odds.insert(&event_id, odds);
+                        self.append_event_bet(&event_id, user_odd).await;
+                    }
+                    Bet::MarketStatusChanged { event_id, suspended } => {
+                        if suspended {
+                            let _ = self.state.suspended_markets.insert(&event_id);
+                        } else {
+                            let _ = self.state.suspended_markets.remove(&event_id);
+                        }
                     }
                 }
             }
@@ -1042,6 +1094,546 @@ impl Contract for ManagementContract {
     }
 }
 
+impl ManagementContract {
+    /// Pays out or closes a single bet on a finished or voided event and updates the bettor's stats.
+    async fn settle_bet(&mut self, user_id: ChainId, event: &Event, bet: &mut UserOdd) {
+        let user_stats = self.state.user_stats.get(&user_id.to_string()).await.expect("Failed to read user stats").unwrap_or_default();
+
+        if event.status.is_void() {
+            // Refund the stake, the bet no longer counts towards the leaderboard
+            bet.status = BetStatus::Cancelled;
+
+            self.pay(user_id, bet.owner, bet.bid, LedgerReason::BetRefund, bet.bet_id.to_string()).await;
+
+            let user_stats = UserStats {
+                total_staked: user_stats.total_staked.saturating_sub(bet.bid),
+                total_bets: user_stats.total_bets.saturating_sub(1),
+                ..user_stats
+            };
+            let _ = self.state.user_stats.insert(&user_id.to_string(), user_stats);
+        } else if bet.selection == event.result.winner {
+            // Calculate prize
+            let prize = calculate_prize(event, bet);
+            bet.status = BetStatus::Won;
+            
+            self.pay(user_id, bet.owner, prize, LedgerReason::BetWin, bet.bet_id.to_string()).await;
+
+            let win_rate = (user_stats.total_wins.saturating_add(1) as f64 / user_stats.total_bets as f64 * 100.0) as u64;
+            let user_stats = UserStats {
+                total_staked: user_stats.total_staked,
+                total_winnings: user_stats.total_winnings.saturating_add(prize),
+                total_bets: user_stats.total_bets,
+                total_wins: user_stats.total_wins.saturating_add(1),
+                total_losses: user_stats.total_losses,
+                win_rate: win_rate,
+            };
+            let _ = self.state.user_stats.insert(&user_id.to_string(), user_stats);
+        } else {
+            bet.status = BetStatus::Lost;
+
+            let win_rate = (user_stats.total_wins as f64 / user_stats.total_bets as f64 * 100.0) as u64;
+            let user_stats = UserStats {
+                total_staked: user_stats.total_staked,
+                total_winnings: user_stats.total_winnings,
+                total_bets: user_stats.total_bets,
+                total_wins: user_stats.total_wins,
+                total_losses: user_stats.total_losses.saturating_add(1),
+                win_rate: win_rate,
+            };
+            let _ = self.state.user_stats.insert(&user_id.to_string(), user_stats);
+        }
+
+        self.runtime.prepare_message(
+            Message::ClaimResult { bet_id: bet.bet_id, result: bet.status }
+        ).with_authentication().send_to(user_id);
+
+    }
+
+    /// Unresolved prediction markets of an event.
+    async fn open_predictions(&self, event_id: &String) -> Vec<LivePrediction> {
+        self.state.event_predictions(event_id).await.expect("Failed to read predictions").into_iter().filter(|p| !p.resolved).collect()
+    }
+
+    /// Voids an event: refunds every open prediction pool to its voters and schedules the
+    /// refund of every open bet through the settlement batches.
+    async fn void_event(&mut self, event_id: String, status: MatchStatus) {
+        let Some(mut event) = self.state.events.get(&event_id).await.expect("Failed to read event") else {
+            return;
+        };
+        if event.status == MatchStatus::Finished || event.status.is_void() {
+            return;
+        }
+
+        event.status = status;
+        event.last_updated = self.runtime.system_time();
+
+        for mut prediction in self.state.event_predictions(&event_id).await.expect("Failed to read predictions").into_iter().filter(|p| p.outcome.is_none()) {
+            prediction.resolved = true;
+            let key = (event_id.clone(), prediction.id);
+            for mut vote in self.state.prediction_votes_of(&key).await.expect("Failed to read prediction votes").into_iter().filter(|v| !v.claimed) {
+                vote.claimed = true;
+                let user_chain_id = ChainId::from_str(&vote.user).unwrap();
+                self.pay(user_chain_id, vote.owner, vote.amount, LedgerReason::PredictionRefund, prediction.id.to_string()).await;
+                self.runtime.prepare_message(
+                    Message::VoteRefunded { prediction_id: prediction.id }
+                ).with_authentication().send_to(user_chain_id);
+                let _ = self.state.prediction_votes.load_entry_mut(&key).await
+                    .expect("Failed to read prediction votes")
+                    .insert(&vote.user.clone(), vote);
+            }
+            let _ = self.state.live_predictions.insert(&key, prediction);
+        }
+
+        let _ = self.state.events.insert(&event_id, event);
+        let _ = self.state.postponed_events.remove(&event_id);
+        let _ = self.state.settlement_cursors.insert(&event_id, 0);
+        self.settle_event_batch(event_id).await;
+    }
+
+    /// Voids postponed events that were not rescheduled within the configured window.
+    async fn void_expired_postponed_events(&mut self) {
+        let window = self.runtime.application_parameters().postponed_void_window.saturating_mul(1_000_000);
+        let now = self.runtime.system_time();
+        let mut expired = Vec::new();
+        self.state.postponed_events.for_each_index_value(|event_id, postponed_at| {
+            if now.micros().saturating_sub(postponed_at.micros()) >= window {
+                expired.push(event_id);
+            }
+            Ok(())
+        }).await.expect("Failed to read postponed events");
+
+        for event_id in expired {
+            self.void_event(event_id, MatchStatus::Cancelled).await;
+        }
+    }
+
+    /// Settles the next batch of open bets on a resolved event, starting at the stored cursor.
+    /// If bets remain, the next batch is scheduled in a later block through a message to this chain.
+    async fn settle_event_batch(&mut self, event_id: String) {
+        let Some(cursor) = self.state.settlement_cursors.get(&event_id).await.expect("Failed to read settlement cursor") else {
+            return;
+        };
+        let event = self.state.events.get(&event_id).await.expect("Event not found").unwrap();
+        let count = self.state.event_bet_counts.get(&event_id).await.expect("Failed to read bet count").unwrap_or_default();
+
+        let batch_size = self.runtime.application_parameters().settlement_batch_size.max(1);
+        let start = cursor.min(count);
+        let end = start.saturating_add(batch_size).min(count);
+
+        for position in start..end {
+            let key = (event_id.clone(), position);
+            let Some(mut bet) = self.state.event_bets.get(&key).await.expect("Failed to read bet") else {
+                continue;
+            };
+            if bet.status == BetStatus::Placed {
+                let user_chain_id = ChainId::from_str(&bet.user_id).unwrap();
+                self.settle_bet(user_chain_id, &event, &mut bet).await;
+                let _ = self.state.event_bets.insert(&key, bet);
+            }
+        }
+
+        if end < count {
+            let _ = self.state.settlement_cursors.insert(&event_id, end);
+            let chain_id = self.runtime.chain_id();
+            self.runtime.prepare_message(
+                Message::SettleEventBatch { event_id }
+            ).with_authentication().send_to(chain_id);
+        } else {
+            let _ = self.state.settlement_cursors.remove(&event_id);
+        }
+    }
+
+    /// A stake message rejected by the management chain comes back to the user chain:
+    /// the bet or vote is cancelled, the failure recorded and the escrowed stake claimed back.
+    async fn handle_bounced_message(&mut self, message: Message) {
+        let (kind, reason, reference, event_id, amount) = match message {
+            Message::NewBetPlaced { bet_id, bid, event_id, .. } => {
+                self.set_user_bet_status(bet_id, BetStatus::Cancelled).await;
+                ("bet", LedgerReason::BetStake, bet_id, event_id, bid)
+            },
+            Message::NewVotePlaced { event_id, prediction_id, amount, .. } => {
+                self.mark_vote_refunded(prediction_id).await;
+                ("vote", LedgerReason::PredictionStake, prediction_id, event_id, amount)
+            },
+            Message::NewPredictionCreated { prediction_id, event_id, amount, .. } => {
+                self.mark_vote_refunded(prediction_id).await;
+                ("prediction", LedgerReason::PredictionStake, prediction_id, event_id, amount)
+            },
+            _ => return,
+        };
+
+        self.state.bounced_stakes.push(BouncedStake {
+            kind: kind.to_string(),
+            reference,
+            event_id,
+            amount,
+            bounced_at: self.runtime.system_time(),
+        });
+
+        let chain_id = self.runtime.application_creator_chain_id();
+        self.runtime.prepare_message(
+            Message::RefundBouncedStake { reason, reference: reference.to_string() }
+        ).with_authentication().send_to(chain_id);
+    }
+
+    async fn mark_vote_refunded(&mut self, prediction_id: u64) {
+        if let Some(mut user_vote) = self.state.user_predictions.get(&prediction_id).await.expect("Failed to read user votes") {
+            user_vote.claimed = true;
+            let _ = self.state.user_predictions.insert(&prediction_id, user_vote);
+        }
+    }
+
+    /// Updates a bet on the user chain and moves it between the open and settled indexes.
+    async fn set_user_bet_status(&mut self, bet_id: u64, status: BetStatus) {
+        let Some(mut user_bet) = self.state.user_bets.get(&bet_id).await.expect("Failed to read bet") else {
+            return;
+        };
+        user_bet.status = status;
+        let _ = self.state.user_bets.insert(&bet_id, user_bet);
+        if status == BetStatus::Placed {
+            let _ = self.state.open_bets.insert(&bet_id);
+            let _ = self.state.settled_bets.remove(&bet_id);
+        } else {
+            let _ = self.state.open_bets.remove(&bet_id);
+            let _ = self.state.settled_bets.insert(&bet_id);
+        }
+    }
+
+    /// Appends the odds now offered on an event to its price history.
+    async fn record_odds(&mut self, event_id: &String, odds: &Odds, reason: OddsChangeReason, bet_id: Option<u64>) {
+        let timestamp = self.runtime.system_time();
+        self.state.odds_history.load_entry_mut(event_id).await
+            .expect("Failed to read odds history")
+            .push(OddsPoint { timestamp, home: odds.home, tie: odds.tie, away: odds.away, reason, bet_id });
+    }
+
+    /// Reprices a live event from its score, minute and red cards, then leans the odds against
+    /// the house exposure. Other events keep their odds.
+    async fn reprice_live(&mut self, event_id: &String, event: &mut Event) {
+        if event.status != MatchStatus::Live {
+            return;
+        }
+        let stakes = self.state.event_stakes.get(event_id).await.expect("Failed to read event stakes").unwrap_or_default();
+        let liabilities = self.state.event_liabilities.get(event_id).await.expect("Failed to read event liabilities").unwrap_or_default();
+        event.odds = self.market_odds(event_id, event, &stakes, &liabilities).await;
+        self.record_odds(event_id, &event.odds, OddsChangeReason::InPlay, None).await;
+    }
+
+    /// Odds offered on an event: its pricing base, carried to the current match state while
+    /// the event is live, moved by the money staked on each selection, then leaned against the
+    /// house exposure. Always computed from the base, so neither adjustment compounds from one
+    /// bet or tick to the next.
+    async fn market_odds(&mut self, event_id: &String, event: &Event, stakes: &SelectionStakes, liabilities: &SelectionStakes) -> Odds {
+        let params = self.runtime.application_parameters();
+        let opening = self.opening_odds(event_id).await.unwrap_or_else(|| event.odds.clone());
+        let base = self.state.pricing_bases.get(event_id).await.expect("Failed to read pricing base")
+            .unwrap_or_else(|| PricingBase { odds: opening.clone(), state: MatchState::default() });
+        let mut odds = (base.odds.home, base.odds.tie, base.odds.away);
+        if event.status == MatchStatus::Live {
+            odds = carry_odds(odds, (opening.home, opening.tie, opening.away), &base.state, &event.match_state(), &params);
+        }
+        let pooled = get_market_odds(odds, stakes, &params);
+        let (home, tie, away) = skew_for_liability(pooled, stakes, liabilities, &params);
+        Odds { home, away, tie }
+    }
+
+    /// Odds the event opened with, the first point of its history.
+    async fn opening_odds(&self, event_id: &String) -> Option<Odds> {
+        let history = self.state.odds_history.try_load_entry(event_id).await.expect("Failed to read odds history")?;
+        let opening = history.get(0).await.expect("Failed to read odds history")?;
+        Some(Odds { home: opening.home, away: opening.away, tie: opening.tie })
+    }
+
+    /// Flags the event's market on the oracle chain and forwards the change to the management chain.
+    async fn set_market_suspended(&mut self, event_id: String, suspended: bool) {
+        let management_chain_id = self.runtime.application_creator_chain_id();
+        let mut event = self.state.events.get(&event_id).await.expect("Event not found").unwrap();
+
+        event.suspended = suspended;
+        event.last_updated = self.runtime.system_time();
+        let _ = self.state.events.insert(&event_id, event);
+
+        self.runtime.prepare_message(
+            Message::MarketSuspensionChanged { event_id, suspended }
+        ).with_authentication().send_to(management_chain_id);
+    }
+
+    /// Appends a bet to the event's bet log and indexes it by user chain and bet id.
+    async fn append_event_bet(&mut self, event_id: &String, bet: UserOdd) {
+        let position = self.state.event_bet_counts.get(event_id).await.expect("Failed to read bet count").unwrap_or_default();
+        let _ = self.state.bet_positions.insert(&(event_id.clone(), bet.user_id.clone(), bet.bet_id), position);
+        let _ = self.state.event_bets.insert(&(event_id.clone(), position), bet);
+        let _ = self.state.event_bet_counts.insert(event_id, position + 1);
+    }
+
+    /// Takes a bet at the current market price, capped to the event's liability limit.
+    async fn accept_bet(&mut self, event_id: String, mut event: Event, pending: PendingBet) {
+        let PendingBet { bet_id, user_id, owner, selection, bid, min_acceptable_odd, .. } = pending;
+        // Settle at the current market price, never at a client supplied odd
+        let params = self.runtime.application_parameters();
+        let odd = event.odds.for_selection(selection);
+        if odd < min_acceptable_odd {
+            self.revert_bet(user_id, owner, bet_id, bid).await;
+            return;
+        }
+
+        // Cap the stake to what the event's liability limit still allows
+        let mut stakes = self.state.event_stakes.get(&event_id).await.expect("Failed to read event stakes").unwrap_or_default();
+        let mut liabilities = self.state.event_liabilities.get(&event_id).await.expect("Failed to read event liabilities").unwrap_or_default();
+        let accepted = bid.min(max_stake(&stakes, &liabilities, selection, odd, &params));
+        if accepted == Amount::ZERO {
+            self.revert_bet(user_id, owner, bet_id, bid).await;
+            return;
+        }
+        if accepted < bid {
+            self.pay(user_id, owner, bid.saturating_sub(accepted), LedgerReason::BetRefund, bet_id.to_string()).await;
+        }
+        let bid = accepted;
+
+        // Update event pool
+        //event.pool = event.pool.saturating_add(bid);
+        //self.state.events.insert(&event_id, event);
+
+        // Record bet
+        let bet = UserOdd {
+            bet_id,
+            user_id: user_id.to_string(),
+            odd,
+            selection,
+            placed_at: self.runtime.system_time(),
+            bid,
+            status: BetStatus::Placed,
+            owner: owner,
+        };
+
+        self.append_event_bet(&event_id, bet.clone()).await;
+        self.runtime.emit(STREAM_NAME.into(), &Bet::NewEventBet { event_id: event_id.clone(), user_odd: bet });
+
+        // Running stake and payout totals keep repricing independent of the number of bets
+        stakes.add(selection, bid);
+        liabilities.add(selection, payout(bid, odd));
+        let _ = self.state.event_stakes.insert(&event_id, stakes.clone());
+        let _ = self.state.event_liabilities.insert(&event_id, liabilities.clone());
+
+        //calculate new odds from the base, then lean them against the house exposure
+        event.odds = self.market_odds(&event_id, &event, &stakes, &liabilities).await;
+        self.record_odds(&event_id, &event.odds, OddsChangeReason::Bet, Some(bet_id)).await;
+        let _ = self.state.events.insert(&event_id, event);
+
+        self.runtime.prepare_message(
+            Message::BetAccepted { bet_id, odd, bid }
+        ).with_authentication().send_to(user_id);
+
+        let user_stats = self.state.user_stats.get(&user_id.to_string()).await.expect("Failed to read user stats").unwrap_or_default();
+        let user_stats = UserStats {
+            total_staked: user_stats.total_staked.saturating_add(bid),
+            total_winnings: user_stats.total_winnings,
+            total_bets: user_stats.total_bets.saturating_add(1),
+            total_wins: user_stats.total_wins,
+            total_losses: user_stats.total_losses,
+            win_rate: user_stats.win_rate,
+        };
+        let _ = self.state.user_stats.insert(&user_id.to_string(), user_stats);
+    }
+
+    /// Decides the in-play bets that have waited out the bet delay: taken at the current price
+    /// if no goal or red card came in around them and the market is still open, refunded otherwise.
+    async fn release_pending_bets(&mut self) {
+        let delay = self.runtime.application_parameters().bet_delay.saturating_mul(1_000_000);
+        let now = self.runtime.system_time();
+        let mut events = Vec::new();
+        self.state.pending_bets.for_each_index(|event_id| {
+            events.push(event_id);
+            Ok(())
+        }).await.expect("Failed to read pending bets");
+
+        for event_id in events {
+            let pending = self.state.pending_bets.get(&event_id).await.expect("Failed to read pending bets").unwrap_or_default();
+            let (due, waiting): (Vec<_>, Vec<_>) = pending.into_iter()
+                .partition(|bet| now.micros().saturating_sub(bet.received_at.micros()) >= delay);
+            if due.is_empty() {
+                continue;
+            }
+            if waiting.is_empty() {
+                let _ = self.state.pending_bets.remove(&event_id);
+            } else {
+                let _ = self.state.pending_bets.insert(&event_id, waiting);
+            }
+
+            for bet in due {
+                let event = self.state.events.get(&event_id).await.expect("Failed to read event");
+                let last_incident = self.state.last_incidents.get(&event_id).await.expect("Failed to read last incident");
+                // Incidents are reported late, so one shortly before the bet counts as well
+                let quiet = event.as_ref().is_some_and(|event| {
+                    event.status == MatchStatus::Live
+                        && !event.suspended
+                        && last_incident.is_none_or(|time| time.micros().saturating_add(delay) < bet.received_at.micros())
+                });
+                match event {
+                    Some(event) if quiet => self.accept_bet(event_id.clone(), event, bet).await,
+                    _ => self.revert_bet(bet.user_id, bet.owner, bet.bet_id, bet.bid).await,
+                }
+            }
+        }
+    }
+
+    /// Reopens the markets a goal suspended once the bet delay has passed.
+    async fn resume_goal_suspensions(&mut self) {
+        let delay = self.runtime.application_parameters().bet_delay.saturating_mul(1_000_000);
+        let now = self.runtime.system_time();
+        let mut expired = Vec::new();
+        self.state.goal_suspensions.for_each_index_value(|event_id, suspended_at| {
+            if now.micros().saturating_sub(suspended_at.micros()) >= delay {
+                expired.push(event_id);
+            }
+            Ok(())
+        }).await.expect("Failed to read goal suspensions");
+
+        for event_id in expired {
+            let _ = self.state.goal_suspensions.remove(&event_id);
+            if let Some(mut event) = self.state.events.get(&event_id).await.expect("Failed to read event") {
+                event.suspended = false;
+                event.last_updated = now;
+                self.reprice_live(&event_id, &mut event).await;
+                let _ = self.state.events.insert(&event_id, event);
+                self.runtime.emit(STREAM_NAME.into(), &Bet::MarketStatusChanged { event_id, suspended: false });
+            }
+        }
+    }
+
+    /// Cancels a bet on the user chain and sends the stake back.
+    async fn revert_bet(&mut self, user_id: ChainId, owner: Option<AccountOwner>, bet_id: u64, bid: Amount) {
+        self.runtime.prepare_message(
+            Message::RevertUserBet { bet_id }
+        ).with_authentication().send_to(user_id);
+        self.pay(user_id, owner, bid, LedgerReason::BetRefund, bet_id.to_string()).await;
+    }
+
+    /// USDL application used for every stake and payout.
+    fn usdl_app_id(&mut self) -> ApplicationId<UsdlAbi> {
+        self.runtime.application_parameters()
+            .usdl_app_id
+            .expect("USDL application is not configured")
+            .with_abi::<UsdlAbi>()
+    }
+
+    /// USDL account of this application on the management chain: escrowed stakes and house funds.
+    fn house_account(&mut self) -> Account {
+        Account {
+            chain_id: self.runtime.application_creator_chain_id(),
+            owner: AccountOwner::from(self.runtime.application_id().forget_abi()),
+        }
+    }
+
+    /// Moves tokens from the signer's USDL account into the escrow on the management chain.
+    fn escrow(&mut self, amount: Amount, reason: LedgerReason, reference: String) {
+        let usdl_app_id = self.usdl_app_id();
+        let owner = self.runtime.authenticated_signer().expect("Operation must be signed");
+        let target_account = self.house_account();
+        let counterparty = target_account.chain_id;
+        self.runtime.call_application(true, usdl_app_id, &UsdlOperation::Transfer { owner, amount, target_account });
+        self.record_transaction(LedgerDirection::Debit, amount, reason, reference.clone(), counterparty);
+
+        // Sent ahead of the stake message, so the management chain knows what it holds
+        self.runtime.prepare_message(
+            Message::StakeEscrowed { reason, reference, amount }
+        ).with_authentication().send_to(counterparty);
+    }
+
+    /// Removes the receipt of an escrowed stake, returning the amount if it was still held.
+    async fn take_escrow_receipt(&mut self, user_id: ChainId, reason: LedgerReason, reference: String) -> Option<Amount> {
+        let key = (user_id.to_string(), reason, reference);
+        let amount = self.state.escrow_receipts.get(&key).await.expect("Failed to read escrow receipts")?;
+        let _ = self.state.escrow_receipts.remove(&key);
+        Some(amount)
+    }
+
+    /// Appends a money movement to this chain's ledger.
+    fn record_transaction(&mut self, direction: LedgerDirection, amount: Amount, reason: LedgerReason, reference: String, counterparty: ChainId) {
+        self.state.ledger.push(LedgerEntry {
+            direction,
+            amount,
+            reason,
+            reference,
+            counterparty,
+            timestamp: self.runtime.system_time(),
+        });
+    }
+
+    /// Pays tokens out of the escrow held on the management chain to the owner of a user chain,
+    /// and tells the user chain why so it can record the credit in its ledger. `owner` falls back
+    /// to the last signer seen for the chain; with neither, the payout waits in `unpaid_payouts`.
+    async fn pay(&mut self, user_id: ChainId, owner: Option<AccountOwner>, amount: Amount, reason: LedgerReason, reference: String) {
+        if amount == Amount::ZERO {
+            return;
+        }
+        let owner = match owner {
+            Some(owner) => owner,
+            None => {
+                let known_owner = self.state.user_owners.get(&user_id.to_string()).await.expect("Failed to read user owner");
+                let Some(owner) = known_owner else {
+                    // Kept until the user chain next signs a message, see record_user_owner
+                    let mut unpaid = self.state.unpaid_payouts.get(&user_id.to_string()).await.expect("Failed to read unpaid payouts").unwrap_or_default();
+                    unpaid.push(UnpaidPayout { amount, reason, reference });
+                    let _ = self.state.unpaid_payouts.insert(&user_id.to_string(), unpaid);
+                    return;
+                };
+                owner
+            }
+        };
+        let usdl_app_id = self.usdl_app_id();
+        let house_owner = self.house_account().owner;
+        let target_account = Account { chain_id: user_id, owner };
+        self.runtime.call_application(true, usdl_app_id, &UsdlOperation::Transfer { owner: house_owner, amount, target_account });
+        self.runtime.prepare_message(
+            Message::Credited { amount, reason, reference }
+        ).with_authentication().send_to(user_id);
+    }
+
+    /// Remembers who signs for each user chain, so payouts reach their USDL account.
+    async fn record_user_owner(&mut self) {
+        let (Some(origin), Some(owner)) = (self.runtime.message_origin_chain_id(), self.runtime.authenticated_signer()) else {
+            return;
+        };
+        if origin != self.runtime.chain_id() {
+            let _ = self.state.user_owners.insert(&origin.to_string(), owner);
+
+            // Pay what was owed while no owner was known
+            let unpaid = self.state.unpaid_payouts.get(&origin.to_string()).await.expect("Failed to read unpaid payouts");
+            if let Some(unpaid) = unpaid {
+                let _ = self.state.unpaid_payouts.remove(&origin.to_string());
+                for payout in unpaid {
+                    self.pay(origin, Some(owner), payout.amount, payout.reason, payout.reference).await;
+                }
+            }
+        }
+    }
+
+    /// Admin operations only run on the management chain and must be signed by the admin owner.
+    fn assert_admin(&mut self) {
+        assert_eq!(
+            self.runtime.chain_id(),
+            self.runtime.application_creator_chain_id(),
+            "Admin operations must be executed on the management chain"
+        );
+        let signer = self.runtime.authenticated_signer();
+        assert!(
+            signer.is_some() && signer == *self.state.admin.get(),
+            "Unauthorized: operation requires the admin owner"
+        );
+    }
+
+    /// Oracle messages are only accepted from chains registered as oracles.
+    fn assert_oracle_origin(&mut self) {
+        let origin = self.runtime.message_origin_chain_id().expect("Message origin chain not available");
+        assert!(
+            self.state.oracles.get().contains(&origin),
+            "Unauthorized: chain {} is not a registered oracle",
+            origin
+        );
+    }
+}
+
 
 /// Calculate prize based on bet amount and odds
 /// Formula: prize = bet_amount * (odd / 100)
@@ -1053,103 +1645,261 @@ fn calculate_prize(_event: &Event, user_bet: &UserOdd) -> Amount {
     Amount::from_attos(prize)
 }
 
-// Retorna (Cuota Local, Cuota Empate, Cuota Visita) escaladas x100
-// Ejemplo: Retorna (188, 355, 370) que significa 1.88, 3.55, 3.70
-pub fn calculate_odds(home: &TeamInfo, away: &TeamInfo) -> (u64, u64, u64) {
-    
-    let base_h = (home.power as i64 + home.form + home.goal_average).max(1) as f64;
-    let base_a = (away.power as i64 + away.form + away.goal_average).max(1) as f64;
-
-    let p_home = base_h * 1.10; 
-    let p_away = base_a * 0.95; 
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
 
-    let delta = (p_home - p_away).abs(); 
-    let max_draw = 0.28; 
-    let min_draw = 0.10; 
-    let decay = 65.0;    
-
-    // A más diferencia (delta), menos probabilidad de empate
-    let mut prob_draw = max_draw * (-delta / decay).exp();
-    if prob_draw < min_draw { prob_draw = min_draw; }
+    use linera_sdk::{
+        linera_base_types::{Amount, ChainId, Timestamp},
+        util::BlockingWait,
+        views::{linera_views, MapView, RegisterView, RootView, View, ViewStorageContext},
+        ContractRuntime,
+    };
+    use management::state::{
+        BetStatus, LeaderboardData, LegacyEvent, LegacyLiveScore, LegacyMatchResult, LegacyLivePrediction, LegacyUserOdd,
+        LegacyUserOdds, LegacyVote, ManagementState, MatchStatus, Odds, OddsChangeReason, Selection, Team, TeamInfo, Teams,
+        UserStats, UserVotes, SCHEMA_VERSION, legacy_bet_id,
+    };
+    use management::{odds::calculate_odds, ManagementParameters};
+    use std::str::FromStr;
+
+    use super::ManagementContract;
+
+    const ORACLE_CHAIN: &str = "e476187f6ddfeb9d588c7b45d3df334d5501d6499b3f9ad5595cae86cce16a65";
+    const USER_A: &str = "69705f85ac4c9fef6c02b4d83426aaaf05154c645ec1c61665f8e450f0468bc0";
+    const USER_B: &str = "256e1dbc00482ddd619c293cc0df94d366afe7980022bb22d99e33036fd465dd";
+
+    /// Views of the first release, at their original positions.
+    #[derive(RootView)]
+    #[view(context = ViewStorageContext)]
+    struct FirstReleaseState {
+        events: MapView<String, LegacyEvent>,
+        event_odds: MapView<String, Vec<LegacyUserOdd>>,
+        oracle: RegisterView<Option<ChainId>>,
+        token_supp: RegisterView<Amount>,
+        leaderboard: RegisterView<LeaderboardData>,
+        power_ranking: MapView<String, TeamInfo>,
+        user_odds: RegisterView<Vec<LegacyUserOdds>>,
+        user_balance: RegisterView<Amount>,
+        nonce: RegisterView<u64>,
+        bonus_claimed: RegisterView<bool>,
+        user_votes: RegisterView<Vec<UserVotes>>,
+    }
 
-    let rem_prob = 1.0 - prob_draw; 
+    fn legacy_bet(user: &str, selection: Selection, placed_at: u64, tokens: u128) -> LegacyUserOdd {
+        LegacyUserOdd {
+            user_id: user.to_string(),
+            odd: 200,
+            selection,
+            placed_at: Timestamp::from(placed_at),
+            bid: Amount::from_tokens(tokens),
+        }
+    }
 
-    let str_h = p_home.powf(2.0);
-    let str_a = p_away.powf(2.0);
-    let total_str = str_h + str_a;
+    /// Writes a management chain as left by the first release and loads it with this build.
+    fn upgraded_management_chain() -> ManagementState {
+        let runtime = ContractRuntime::<ManagementContract>::new();
+        let mut legacy = FirstReleaseState::load(runtime.root_view_storage_context())
+            .blocking_wait()
+            .expect("Failed to read from mock key value store");
+
+        let event = LegacyEvent {
+            id: "e1".to_string(),
+            status: MatchStatus::Finished,
+            result: LegacyMatchResult { winner: Selection::Home, home_score: "2".to_string(), away_score: "1".to_string() },
+            live_score: LegacyLiveScore { home: "2".to_string(), away: "1".to_string(), updated_at: Timestamp::from(50) },
+            predictions: vec![LegacyLivePrediction {
+                id: 7,
+                creator: USER_A.to_string(),
+                pool_yes: Amount::from_tokens(5),
+                votes: vec![LegacyVote { user: USER_A.to_string(), amount: Amount::from_tokens(5), choice: true, claimed: false }],
+                ..LegacyLivePrediction::default()
+            }],
+            ..LegacyEvent::default()
+        };
+        legacy.events.insert(&"e1".to_string(), event).unwrap();
+        legacy.event_odds.insert(&"e1".to_string(), vec![
+            legacy_bet(USER_A, Selection::Home, 10, 3),
+            legacy_bet(USER_B, Selection::Away, 20, 4),
+            legacy_bet(USER_A, Selection::Tie, 30, 1),
+        ]).unwrap();
+        legacy.event_odds.insert(&"e0".to_string(), vec![legacy_bet(USER_A, Selection::Home, 5, 2)]).unwrap();
+
+        // A scheduled event whose odds already lean against the bets it took
+        let team = |id: &str| Team { name: id.to_string(), id: id.to_string() };
+        let scheduled = LegacyEvent {
+            id: "e2".to_string(),
+            teams: Teams { home: team("h"), away: team("a") },
+            odds: Odds { home: 150, away: 900, tie: 700 },
+            ..LegacyEvent::default()
+        };
+        legacy.events.insert(&"e2".to_string(), scheduled).unwrap();
+        legacy.event_odds.insert(&"e2".to_string(), vec![legacy_bet(USER_B, Selection::Away, 40, 50)]).unwrap();
+        for (id, power) in [("h", 80), ("a", 70)] {
+            legacy.power_ranking.insert(&id.to_string(), TeamInfo { id: id.to_string(), name: id.to_string(), power, ..TeamInfo::default() }).unwrap();
+        }
+        legacy.oracle.set(Some(ChainId::from_str(ORACLE_CHAIN).unwrap()));
+        let mut user_stats = HashMap::new();
+        user_stats.insert(USER_A.to_string(), UserStats { total_bets: 3, ..UserStats::default() });
+        legacy.leaderboard.set(LeaderboardData { week: 12, year: 2025, user_stats, prize_pool: Amount::from_tokens(100), ..LeaderboardData::default() });
+        legacy.save().blocking_wait().expect("Failed to save the fixture");
+
+        let mut state = ManagementState::load(runtime.root_view_storage_context())
+            .blocking_wait()
+            .expect("Failed to load the upgraded state");
+        state.migrate(&ManagementParameters::default()).blocking_wait();
+        state
+    }
 
-    let prob_home = (str_h / total_str) * rem_prob;
-    let prob_away = (str_a / total_str) * rem_prob;
+    #[test]
+    fn first_release_state_migrates() {
+        let state = upgraded_management_chain();
+        assert_eq!(*state.schema_version.get(), SCHEMA_VERSION);
+
+        // Roles
+        assert_eq!(*state.oracles.get(), vec![ChainId::from_str(ORACLE_CHAIN).unwrap()]);
+        assert_eq!(*state.oracle.get(), None);
+
+        // Events keep their data with numeric scores and lose the embedded predictions
+        let event = state.events.get(&"e1".to_string()).blocking_wait().unwrap().expect("Event not migrated");
+        assert_eq!((event.result.home_score, event.result.away_score), (2, 1));
+        assert_eq!((event.live_score.home, event.live_score.away), (2, 1));
+        assert!(!event.suspended);
+        assert_eq!(state.legacy_events.count().blocking_wait().unwrap(), 0);
+
+        let key = ("e1".to_string(), 7);
+        let prediction = state.live_predictions.get(&key).blocking_wait().unwrap().expect("Prediction not migrated");
+        assert!(prediction.votes.is_empty());
+        assert_eq!(state.prediction_votes_of(&key).blocking_wait().unwrap().len(), 1);
+
+        // Bets are numbered by their rank among the user chain's bets on the event
+        assert_eq!(state.event_bet_counts.get(&"e1".to_string()).blocking_wait().unwrap(), Some(3));
+        let position_of = |user: &str, bet_id: u64| {
+            state.bet_positions.get(&("e1".to_string(), user.to_string(), bet_id)).blocking_wait().unwrap()
+        };
+        assert_eq!(position_of(USER_A, legacy_bet_id("e1", 0)), Some(0));
+        assert_eq!(position_of(USER_B, legacy_bet_id("e1", 0)), Some(1));
+        assert_eq!(position_of(USER_A, legacy_bet_id("e1", 1)), Some(2));
+        assert_eq!(state.event_odds.count().blocking_wait().unwrap(), 0);
+
+        let stakes = state.event_stakes.get(&"e1".to_string()).blocking_wait().unwrap().unwrap();
+        assert_eq!(stakes.total(), Amount::from_tokens(8));
+        let liabilities = state.event_liabilities.get(&"e1".to_string()).blocking_wait().unwrap().unwrap();
+        assert_eq!(liabilities.get(Selection::Away), Amount::from_tokens(8));
+
+        // Events without an odds history reopen from the team ratings, not their skewed odds
+        let history = state.odds_history.try_load_entry(&"e2".to_string()).blocking_wait().unwrap().expect("No odds history");
+        let opening = history.get(0).blocking_wait().unwrap().expect("No opening odds");
+        let expected = calculate_odds(
+            &state.power_ranking.get(&"h".to_string()).blocking_wait().unwrap().unwrap(),
+            &state.power_ranking.get(&"a".to_string()).blocking_wait().unwrap().unwrap(),
+            &ManagementParameters::default(),
+        );
+        assert_eq!((opening.home, opening.tie, opening.away), expected);
+        assert_eq!(opening.reason, OddsChangeReason::Opening);
+
+        // Leaderboard
+        assert_eq!(state.leaderboard_round.get().week, 12);
+        assert_eq!(state.user_stats.get(&USER_A.to_string()).blocking_wait().unwrap().unwrap().total_bets, 3);
+    }
 
-    // --- 5. Convertir a Cuota + Margen (8%) ---
-    let margin = 1.08;
-    
-    // Cuota = (1 / Probabilidad) / Margen
-    let raw_odd_h = (1.0 / prob_home) / margin;
-    let raw_odd_d = (1.0 / prob_draw) / margin;
-    let raw_odd_a = (1.0 / prob_away) / margin;
-
-    // Ejemplo: 1.884 -> 188.4 -> 188
-    (
-        (raw_odd_h * 100.0) as u64,
-        (raw_odd_d * 100.0) as u64,
-        (raw_odd_a * 100.0) as u64
-    )
-}
+    #[test]
+    fn first_release_user_history_migrates() {
+        let runtime = ContractRuntime::<ManagementContract>::new();
+        let mut legacy = FirstReleaseState::load(runtime.root_view_storage_context())
+            .blocking_wait()
+            .expect("Failed to read from mock key value store");
+        legacy.user_odds.set(vec![
+            LegacyUserOdds { event_id: "e0".to_string(), status: BetStatus::Won, ..LegacyUserOdds::default() },
+            LegacyUserOdds { event_id: "e1".to_string(), status: BetStatus::Placed, ..LegacyUserOdds::default() },
+        ]);
+        legacy.user_votes.set(vec![UserVotes { id: 7, event_id: "e1".to_string(), ..UserVotes::default() }]);
+        legacy.save().blocking_wait().expect("Failed to save the fixture");
+
+        let mut state = ManagementState::load(runtime.root_view_storage_context())
+            .blocking_wait()
+            .expect("Failed to load the upgraded state");
+        state.migrate(&ManagementParameters::default()).blocking_wait();
+
+        let (won, placed) = (legacy_bet_id("e0", 0), legacy_bet_id("e1", 0));
+        let first = state.user_bets.get(&won).blocking_wait().unwrap().expect("Won bet not migrated");
+        assert_eq!((first.event_id.as_str(), first.status), ("e0", BetStatus::Won));
+        let second = state.user_bets.get(&placed).blocking_wait().unwrap().expect("Placed bet not migrated");
+        assert_eq!(second.event_id, "e1");
+        assert!(state.settled_bets.contains(&won).blocking_wait().unwrap());
+        assert!(state.open_bets.contains(&placed).blocking_wait().unwrap());
+        assert_eq!(*state.nonce.get(), 0);
+        assert!(state.user_predictions.contains_key(&7).blocking_wait().unwrap());
+        assert!(state.user_odds.get().is_empty());
+    }
 
-pub fn get_market_odds(initial_odds: (u64, u64, u64), current_bets: &Vec<UserOdd>) -> (u64, u64, u64) {
-    
-    // 1. Constantes
-    const VIRTUAL_LIQUIDITY: f64 = 1_000.0;
-    const MARGIN: f64 = 1.08;
-    const TOKEN_DECIMALS: f64 = 1_000_000_000_000_000_000.0; 
-
-    // 2. Probabilidades Base
-    // .max(1) evita división por cero si el input viene mal
-    let p_base_h = 100.0 / (initial_odds.0.max(1) as f64); 
-    let p_base_t = 100.0 / (initial_odds.1.max(1) as f64);
-    let p_base_a = 100.0 / (initial_odds.2.max(1) as f64);
-
-    let mut pool_h = VIRTUAL_LIQUIDITY * p_base_h;
-    let mut pool_t = VIRTUAL_LIQUIDITY * p_base_t;
-    let mut pool_a = VIRTUAL_LIQUIDITY * p_base_a;
-
-    // 3. Sumar apuestas
-    for bet in current_bets {
-        let amount_normalized = u128::from(bet.bid) as f64 / TOKEN_DECIMALS;
-        
-        match bet.selection {
-            Selection::Home => pool_h += amount_normalized,
-            Selection::Tie  => pool_t += amount_normalized,
-            Selection::Away => pool_a += amount_normalized,
+    #[test]
+    fn legacy_bet_ids_agree_across_chains() {
+        // User A bet twice on e1 and once on e3; a bet on e2 in between was reverted
+        let user_runtime = ContractRuntime::<ManagementContract>::new();
+        let mut user_chain = FirstReleaseState::load(user_runtime.root_view_storage_context())
+            .blocking_wait()
+            .expect("Failed to read from mock key value store");
+        let user_bet = |event_id: &str, status: BetStatus| LegacyUserOdds { event_id: event_id.to_string(), status, ..LegacyUserOdds::default() };
+        user_chain.user_odds.set(vec![
+            user_bet("e1", BetStatus::Placed),
+            user_bet("e2", BetStatus::Cancelled),
+            user_bet("e1", BetStatus::Placed),
+            user_bet("e3", BetStatus::Placed),
+        ]);
+        user_chain.save().blocking_wait().expect("Failed to save the fixture");
+
+        let management_runtime = ContractRuntime::<ManagementContract>::new();
+        let mut management_chain = FirstReleaseState::load(management_runtime.root_view_storage_context())
+            .blocking_wait()
+            .expect("Failed to read from mock key value store");
+        management_chain.event_odds.insert(&"e1".to_string(), vec![
+            legacy_bet(USER_A, Selection::Home, 10, 1),
+            legacy_bet(USER_B, Selection::Away, 15, 1),
+            legacy_bet(USER_A, Selection::Away, 30, 1),
+        ]).unwrap();
+        management_chain.event_odds.insert(&"e3".to_string(), vec![legacy_bet(USER_A, Selection::Tie, 40, 1)]).unwrap();
+        management_chain.save().blocking_wait().expect("Failed to save the fixture");
+
+        let mut user_state = ManagementState::load(user_runtime.root_view_storage_context())
+            .blocking_wait()
+            .expect("Failed to load the upgraded state");
+        let unmigrated = user_state.legacy_user_bets();
+        user_state.migrate(&ManagementParameters::default()).blocking_wait();
+        let mut management_state = ManagementState::load(management_runtime.root_view_storage_context())
+            .blocking_wait()
+            .expect("Failed to load the upgraded state");
+        management_state.migrate(&ManagementParameters::default()).blocking_wait();
+
+        // The service reads the same ids from the legacy vector before the chain migrates
+        let mut bet_ids = user_state.user_bets.indices().blocking_wait().unwrap();
+        bet_ids.sort();
+        let mut unmigrated_ids: Vec<u64> = unmigrated.iter().map(|bet| bet.bet_id).collect();
+        unmigrated_ids.sort();
+        assert_eq!(bet_ids, unmigrated_ids);
+
+        // Every bet the management chain stored is found under the id the user chain holds
+        for bet in unmigrated {
+            let position = management_state.bet_positions
+                .get(&(bet.event_id.clone(), USER_A.to_string(), bet.bet_id))
+                .blocking_wait()
+                .unwrap();
+            assert_eq!(position.is_some(), bet.status == BetStatus::Placed, "bet on {}", bet.event_id);
         }
+        let position_of = |event_id: &str, ordinal: u64| {
+            management_state.bet_positions
+                .get(&(event_id.to_string(), USER_A.to_string(), legacy_bet_id(event_id, ordinal)))
+                .blocking_wait()
+                .unwrap()
+        };
+        assert_eq!(position_of("e1", 1), Some(2));
+        assert_eq!(position_of("e3", 0), Some(0));
     }
 
-    let total_pool = pool_h + pool_t + pool_a;
-
-    // 4. Calcular Probabilidades (Con protección contra 0)
-    let (raw_prob_h, raw_prob_t, raw_prob_a) = if total_pool > 0.0 {
-        (
-            pool_h / total_pool,
-            pool_t / total_pool,
-            pool_a / total_pool
-        )
-    } else {
-        (0.33, 0.33, 0.33) // Fallback imposible pero seguro
-    };
-
-    // 5. Función auxiliar para calcular cuota segura
-    // Evita el 0.92 y el Overflow
-    let calc_safe_odd = |prob: f64| -> u64 {
-        let safe_prob = prob.clamp(0.01, 0.98);
-        let odd_decimal = (1.0 / safe_prob) / MARGIN;
-        let odd_u64 = (odd_decimal * 100.0) as u64;
-        odd_u64.clamp(101, 10_000)
-    };
-
-    (
-        calc_safe_odd(raw_prob_h),
-        calc_safe_odd(raw_prob_t),
-        calc_safe_odd(raw_prob_a)
-    )
-}
\ No newline at end of file
+    #[test]
+    fn migration_runs_once() {
+        let mut state = upgraded_management_chain();
+        state.migrate(&ManagementParameters::default()).blocking_wait();
+        assert_eq!(state.event_bet_counts.get(&"e1".to_string()).blocking_wait().unwrap(), Some(3));
+    }
+}
@@ -1,14 +1,15 @@
 
+pub mod odds;
 pub mod state;
 
-use async_graphql::{Request, Response};
+use async_graphql::{Request, Response, SimpleObject};
 use linera_sdk::{
     graphql::GraphQLMutationRoot,
-    linera_base_types::{ContractAbi, ServiceAbi, Amount, Timestamp, ChainId},
+    linera_base_types::{ContractAbi, ServiceAbi, Amount, Timestamp, ChainId, AccountOwner, ApplicationId},
 };
 use serde::{Deserialize, Serialize};
 
-pub use self::state::{Event, UserOdd, PredictionType};
+pub use self::state::{Event, UserOdd, PredictionType, MatchStatus, TypeEvent, Selection, BetStatus, MatchEventType};
 pub struct ManagementAbi;
 
 impl ContractAbi for ManagementAbi {
@@ -21,19 +22,93 @@ impl ServiceAbi for ManagementAbi {
     type QueryResponse = Response;
 }
 
+/// Market economics, fixed for the lifetime of the application.
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+#[serde(default)]
+pub struct ManagementParameters {
+    /// USDL application holding stakes, payouts and the house funds. Required to bet.
+    pub usdl_app_id: Option<ApplicationId>,
+    /// Bookmaker margin scaled x100 (108 = 8% overround), at least 100.
+    pub margin: u64,
+    /// Virtual tokens seeded into each outcome pool when repricing.
+    pub virtual_liquidity: u64,
+    /// Lowest odd offered, scaled x100.
+    pub min_odd: u64,
+    /// Highest odd offered, scaled x100, not below `min_odd`.
+    pub max_odd: u64,
+    /// Most the house may lose on a single event; bets beyond it are capped. Zero disables the limit.
+    pub max_event_liability: Amount,
+    /// Percent an odd is cut when the house loss on that selection reaches `max_event_liability`.
+    pub liability_skew: u64,
+    /// Seconds an in-play bet is held before it is taken, refunded if a goal, red card or score
+    /// change comes in meanwhile. Also how long a goal keeps the market suspended.
+    pub bet_delay: u64,
+    /// Weekly prize pool split in percent, by leaderboard rank, adding up to at most 100.
+    pub leaderboard_split: Vec<u64>,
+    /// USDL paid once per user chain from the house funds by `RequestMint`.
+    pub welcome_bonus: Amount,
+    /// Push payouts and results to every bettor as soon as an event is resolved.
+    pub auto_settle: bool,
+    /// Bets settled per block while pushing results of a resolved event.
+    pub settlement_batch_size: u64,
+    /// Seconds a postponed event may wait for a new date before it is voided.
+    pub postponed_void_window: u64,
+}
+
+impl Default for ManagementParameters {
+    fn default() -> Self {
+        ManagementParameters {
+            usdl_app_id: None,
+            margin: 108,
+            virtual_liquidity: 1_000,
+            min_odd: 101,
+            max_odd: 10_000,
+            max_event_liability: Amount::from_tokens(10_000),
+            liability_skew: 20,
+            bet_delay: 10,
+            leaderboard_split: vec![50, 30, 20],
+            welcome_bonus: Amount::from_tokens(100),
+            auto_settle: false,
+            settlement_batch_size: 50,
+            postponed_void_window: 7 * 24 * 60 * 60,
+        }
+    }
+}
+
+/// Roles registered when the application is created on the management chain.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct InstantiationArgument {
+    /// Chains allowed to create, update and resolve events.
+    pub oracles: Vec<ChainId>,
+    /// Owner allowed to manage the oracle list and rotate the admin role.
+    pub admin: AccountOwner,
+    /// USDL minted into the house funds at creation, must not be zero. Requires the creator to be
+    /// the USDL minter.
+    #[serde(default)]
+    pub initial_treasury: Amount,
+}
+
 #[derive(Debug, Deserialize, Serialize, GraphQLMutationRoot)]
 pub enum Operation {
     //appchain
     Subscribe { chain_id: ChainId },
     Unsubscribe { chain_id: ChainId },
-    CreateEvent { id: String, type_event: String, league: String, home_id: String, away_id: String, start_time: Timestamp },
-    UpdateEventStatus { event_id: String, status: String },
+    //admin operations
+    AddOracle { chain_id: ChainId },
+    RemoveOracle { chain_id: ChainId },
+    TransferAdmin { new_admin: AccountOwner },
+    CreateEvent { id: String, type_event: TypeEvent, league: String, home_id: String, away_id: String, start_time: Timestamp },
+    UpdateEventStatus { event_id: String, status: MatchStatus },
     UpdateCurrentMinute { event_id: String, current_minute: u32 },
-    ResolveEvent { event_id: String, winner: String, home_score: String, away_score: String },
-    UpdateEventLiveScore { event_id: String, home_score: String, away_score: String },
+    ResolveEvent { event_id: String, winner: Selection, home_score: u8, away_score: u8 },
+    VoidEvent { event_id: String },
+    UpdateEventLiveScore { event_id: String, home_score: u8, away_score: u8 },
+    UpdateEventOdds { event_id: String, home_odds: u64, away_odds: u64, tie_odds: u64 },
+    SuspendMarket { event_id: String },
+    ResumeMarket { event_id: String },
     AddMatchEvent { 
         event_id: String, 
-        event_type: String,
+        event_type: MatchEventType,
         time: String,
         team: String,
         player: Option<String>,
@@ -48,33 +123,43 @@ pub enum Operation {
     //userChain
     CreatePrediction { prediction_id: u64, event_id: String, prediction_type: PredictionType, question: String, init_vote: bool, amount: Amount },
     PlaceVote { event_id: String, prediction_id: u64, vote: bool, amount: Amount, prediction_type: PredictionType },
-    PlaceBet{ home_id: String, away_id: String, home_name: String, away_name: String, league: String, start_time: Timestamp, odd: u64, selection: String, bid: Amount, event_id: String},
-    ClaimReward { event_id: String },
+    PlaceBet{ home_id: String, away_id: String, home_name: String, away_name: String, league: String, start_time: Timestamp, min_acceptable_odd: u64, selection: Selection, bid: Amount, event_id: String},
+    ClaimReward { bet_id: u64 },
     ClaimPredictionReward { prediction_id: u64, event_id: String },
     RequestMint { amount: Amount },
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub enum Message {
-   NewBetPlaced { home: String, away: String, league: String, start_time: Timestamp, odd: u64, selection: String, bid: Amount, status: String, event_id: String },
+   NewBetPlaced { bet_id: u64, home: String, away: String, league: String, start_time: Timestamp, min_acceptable_odd: u64, selection: Selection, bid: Amount, status: BetStatus, event_id: String },
+   BetAccepted { bet_id: u64, odd: u64, bid: Amount },
    NewPredictionCreated { prediction_id: u64, event_id: String, prediction_type: PredictionType, question: String, init_vote: bool, amount: Amount },
    NewVotePlaced { event_id: String, prediction_id: u64, vote: bool, amount: Amount },
-   RevertUserBet { event_id: String },
-   UserClaimReward { event_id: String },
+   RevertUserBet { bet_id: u64 },
+   VoteRefunded { prediction_id: u64 },
+   UserClaimReward { event_id: String, bet_id: u64 },
    SendPredictionReward { prediction_id: u64, event_id: String },
-   ClaimResult { event_id: String, result: String},
-   MintTokens { amount: Amount },
-   Receive { amount: Amount },
+   ClaimResult { bet_id: u64, result: BetStatus },
+   ClaimWelcomeBonus { amount: Amount },
+   // stake moved into the escrow, a bounced stake is only refunded against it
+   StakeEscrowed { reason: state::LedgerReason, reference: String, amount: Amount },
+   RefundBouncedStake { reason: state::LedgerReason, reference: String },
+   // payout sent to a user chain, recorded in its ledger
+   Credited { amount: Amount, reason: state::LedgerReason, reference: String },
    //power ranking cross-messages
    UpdateTeamPower { team_id: String, name: String, power: u64, form: i64, goal_average: i64 },
    //toAppChain
    NewEventCreated{event_id: String, event: Event},
    //Delta messages
    EventStatusUpdated { event_id: String, status: state::MatchStatus },
-   EventScoreUpdated { event_id: String, home_score: String, away_score: String },
+   EventScoreUpdated { event_id: String, home_score: u8, away_score: u8 },
    EventMinuteUpdated { event_id: String, minute: u32 },
    EventMatchEventAdded { event_id: String, match_event: state::MatchEvent },
-   EventOutcomeResolved { event_id: String, winner: state::Selection, home_score: String, away_score: String },
+   EventOddsUpdated { event_id: String, home: u64, away: u64, tie: u64 },
+   MarketSuspensionChanged { event_id: String, suspended: bool },
+   EventOutcomeResolved { event_id: String, winner: Selection, home_score: u8, away_score: u8 },
+   EventVoided { event_id: String, status: MatchStatus },
+   SettleEventBatch { event_id: String },
    //leaderboard cross-messages
    NewWeekStarted { week: u64, year: u64, prize_pool: Amount },
    CurrentWeekEnded { week: u64, year: u64},
@@ -83,5 +168,6 @@ pub enum Message {
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Bet {
     NewEventBet { event_id: String, user_odd: UserOdd },
+    MarketStatusChanged { event_id: String, suspended: bool },
 }
 
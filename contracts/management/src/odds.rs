@@ -0,0 +1,466 @@
+//! Odds engine on integer arithmetic, so every validator computes the same prices.
+//!
+//! Probabilities are fixed point with 18 decimals (`ONE` = 100%), the same scale as `Amount`
+//! attos, and odds are quoted scaled x100 (188 = 1.88).
+
+use std::cmp::Ordering;
+
+use linera_sdk::linera_base_types::Amount;
+
+use crate::{ManagementParameters, state::{selection_loss, MatchState, Selection, SelectionStakes, TeamInfo}};
+
+/// 1.0 in fixed point.
+pub const ONE: u128 = 1_000_000_000_000_000_000;
+
+/// ln(2) in fixed point.
+const LN_2: u128 = 693_147_180_559_945_309;
+
+/// Goals expected over a full match, 2.6 in fixed point.
+const MATCH_GOALS: u128 = 2_600_000_000_000_000_000;
+
+/// Minutes of regular time.
+const MATCH_MINUTES: u128 = 90;
+
+/// Most goals per side counted for the rest of a match.
+const MAX_GOALS: usize = 10;
+
+/// `value * ONE / total`, dividing the denominator first when the product would overflow.
+pub fn ratio(value: u128, total: u128) -> u128 {
+    if total == 0 {
+        return 0;
+    }
+    match value.checked_mul(ONE) {
+        Some(scaled) => scaled / total,
+        None => value / (total / ONE).max(1),
+    }
+}
+
+/// Product of two fixed point numbers.
+pub fn mul(a: u128, b: u128) -> u128 {
+    a.saturating_mul(b) / ONE
+}
+
+/// exp(-x) for a fixed point `x >= 0`.
+///
+/// x is split into k·ln2 + r with 0 <= r < ln2, so exp(-x) = exp(-r) / 2^k, and exp(-r) is
+/// summed from its Taylor series until the terms vanish.
+pub fn exp_neg(x: u128) -> u128 {
+    let k = x / LN_2;
+    if k >= 64 {
+        return 0;
+    }
+    let r = x % LN_2;
+
+    let mut sum = ONE;
+    let mut term = ONE;
+    let mut n = 1u128;
+    while term > 0 {
+        term = mul(term, r) / n;
+        sum = if n % 2 == 1 { sum - term } else { sum + term };
+        n += 1;
+    }
+    sum >> k
+}
+
+/// Odd scaled x100 that prices a probability with the configured margin, within the bounds.
+pub fn odd_from_probability(probability: u128, params: &ManagementParameters) -> u64 {
+    // Cuota = (1 / Probabilidad) / Margen
+    let denominator = probability.max(1).saturating_mul(params.margin.max(1) as u128);
+    let odd = (100 * 100 * ONE) / denominator;
+    (odd.min(u64::MAX as u128) as u64).clamp(params.min_odd, params.max_odd)
+}
+
+/// Probability implied by an odd scaled x100.
+pub fn probability_from_odd(odd: u64) -> u128 {
+    100 * ONE / odd.max(1) as u128
+}
+
+// Retorna (Cuota Local, Cuota Empate, Cuota Visita) escaladas x100
+// Ejemplo: Retorna (188, 355, 370) que significa 1.88, 3.55, 3.70
+pub fn calculate_odds(home: &TeamInfo, away: &TeamInfo, params: &ManagementParameters) -> (u64, u64, u64) {
+    let base_h = (home.power as i64 + home.form + home.goal_average).max(1) as u128;
+    let base_a = (away.power as i64 + away.form + away.goal_average).max(1) as u128;
+
+    // Home advantage: x1.10 for the home side, x0.95 for the away side
+    let p_home = base_h * 110;
+    let p_away = base_a * 95;
+
+    let max_draw = ONE * 28 / 100;
+    let min_draw = ONE * 10 / 100;
+    let decay = 65 * 100;
+
+    // A más diferencia (delta), menos probabilidad de empate
+    let delta = p_home.abs_diff(p_away);
+    let prob_draw = mul(max_draw, exp_neg(ratio(delta, decay))).max(min_draw);
+
+    let rem_prob = ONE - prob_draw;
+
+    let str_h = p_home * p_home;
+    let str_a = p_away * p_away;
+    let total_str = str_h + str_a;
+
+    let prob_home = mul(ratio(str_h, total_str), rem_prob);
+    let prob_away = mul(ratio(str_a, total_str), rem_prob);
+
+    (
+        odd_from_probability(prob_home, params),
+        odd_from_probability(prob_draw, params),
+        odd_from_probability(prob_away, params)
+    )
+}
+
+pub fn get_market_odds(initial_odds: (u64, u64, u64), stakes: &SelectionStakes, params: &ManagementParameters) -> (u64, u64, u64) {
+    // Probabilidades base, seeded as virtual liquidity (in attos) into each pool
+    let virtual_liquidity = params.virtual_liquidity as u128;
+    let mut pool_h = virtual_liquidity.saturating_mul(probability_from_odd(initial_odds.0));
+    let mut pool_t = virtual_liquidity.saturating_mul(probability_from_odd(initial_odds.1));
+    let mut pool_a = virtual_liquidity.saturating_mul(probability_from_odd(initial_odds.2));
+
+    // Sumar apuestas
+    pool_h = pool_h.saturating_add(u128::from(stakes.home));
+    pool_t = pool_t.saturating_add(u128::from(stakes.tie));
+    pool_a = pool_a.saturating_add(u128::from(stakes.away));
+
+    let total_pool = pool_h.saturating_add(pool_t).saturating_add(pool_a);
+
+    let (raw_prob_h, raw_prob_t, raw_prob_a) = if total_pool > 0 {
+        (ratio(pool_h, total_pool), ratio(pool_t, total_pool), ratio(pool_a, total_pool))
+    } else {
+        (ONE / 3, ONE / 3, ONE / 3)
+    };
+
+    // Evita el 0.92 y el Overflow
+    let calc_safe_odd = |prob: u128| -> u64 {
+        let safe_prob = prob.clamp(ONE / 100, ONE * 98 / 100);
+        odd_from_probability(safe_prob, params)
+    };
+
+    (
+        calc_safe_odd(raw_prob_h),
+        calc_safe_odd(raw_prob_t),
+        calc_safe_odd(raw_prob_a)
+    )
+}
+
+/// Poisson probabilities of 0..=MAX_GOALS goals for a fixed point rate.
+fn poisson(lambda: u128) -> [u128; MAX_GOALS + 1] {
+    let mut pmf = [0; MAX_GOALS + 1];
+    let mut term = exp_neg(lambda);
+    for (k, p) in pmf.iter_mut().enumerate() {
+        if k > 0 {
+            term = mul(term, lambda) / k as u128;
+        }
+        *p = term;
+    }
+    pmf
+}
+
+/// In-play (home, tie, away) odds scaled x100 from the opening odds, the score, the minute and
+/// the red cards of each side.
+///
+/// The opening odds split `MATCH_GOALS` between the sides, the rate left is proportional to
+/// the minutes left, and each red card cuts the side's rate by a quarter and lifts the
+/// opponent's by a fifth. The remaining goals of each side are Poisson distributed.
+pub fn live_odds(opening: (u64, u64, u64), score: (u8, u8), minute: u32, red_cards: (u32, u32), params: &ManagementParameters) -> (u64, u64, u64) {
+    let (prob_h, prob_t, prob_a) = live_probabilities(opening, score, minute, red_cards);
+    (
+        odd_from_probability(prob_h, params),
+        odd_from_probability(prob_t, params),
+        odd_from_probability(prob_a, params)
+    )
+}
+
+/// Carries `odds` quoted at match state `from` over to `to`. Each implied probability is
+/// scaled by how far the live model moved it between the two states, then the three are
+/// normalised again, so an oracle override keeps its view of the match as play goes on.
+pub fn carry_odds(odds: (u64, u64, u64), opening: (u64, u64, u64), from: &MatchState, to: &MatchState, params: &ManagementParameters) -> (u64, u64, u64) {
+    let then = live_probabilities(opening, from.score, from.minute, from.red_cards);
+    let now = live_probabilities(opening, to.score, to.minute, to.red_cards);
+    let shift = |odd: u64, then: u128, now: u128| mul(probability_from_odd(odd), ratio(now, then.max(1)));
+    let prob_h = shift(odds.0, then.0, now.0);
+    let prob_t = shift(odds.1, then.1, now.1);
+    let prob_a = shift(odds.2, then.2, now.2);
+
+    let total = prob_h + prob_t + prob_a;
+    if total == 0 {
+        return live_odds(opening, to.score, to.minute, to.red_cards, params);
+    }
+    (
+        odd_from_probability(ratio(prob_h, total), params),
+        odd_from_probability(ratio(prob_t, total), params),
+        odd_from_probability(ratio(prob_a, total), params)
+    )
+}
+
+/// Probabilities of a home win, a tie and an away win under the live model of `live_odds`.
+fn live_probabilities(opening: (u64, u64, u64), score: (u8, u8), minute: u32, red_cards: (u32, u32)) -> (u128, u128, u128) {
+    let strength_h = probability_from_odd(opening.0);
+    let strength_a = probability_from_odd(opening.2);
+    let share_h = ratio(strength_h, strength_h + strength_a);
+
+    // Stoppage time keeps a minute worth of goals in play
+    let remaining = ratio(MATCH_MINUTES.saturating_sub(minute as u128).max(1), MATCH_MINUTES);
+    let mut lambda_h = mul(mul(MATCH_GOALS, share_h), remaining);
+    let mut lambda_a = mul(mul(MATCH_GOALS, ONE - share_h), remaining);
+
+    for _ in 0..red_cards.0.min(5) {
+        lambda_h = lambda_h * 3 / 4;
+        lambda_a = lambda_a * 6 / 5;
+    }
+    for _ in 0..red_cards.1.min(5) {
+        lambda_a = lambda_a * 3 / 4;
+        lambda_h = lambda_h * 6 / 5;
+    }
+
+    let lead = score.0 as i64 - score.1 as i64;
+    let (mut prob_h, mut prob_t, mut prob_a) = (0u128, 0u128, 0u128);
+    for (i, p_i) in poisson(lambda_h).iter().enumerate() {
+        for (j, p_j) in poisson(lambda_a).iter().enumerate() {
+            let p = mul(*p_i, *p_j);
+            match (lead + i as i64 - j as i64).cmp(&0) {
+                Ordering::Greater => prob_h += p,
+                Ordering::Equal => prob_t += p,
+                Ordering::Less => prob_a += p,
+            }
+        }
+    }
+    (prob_h, prob_t, prob_a)
+}
+
+/// Largest stake on `selection` at `odd` that keeps the house loss on the event within
+/// `max_event_liability`. Unlimited when the limit is disabled or the odd pays no profit.
+pub fn max_stake(stakes: &SelectionStakes, liabilities: &SelectionStakes, selection: Selection, odd: u64, params: &ManagementParameters) -> Amount {
+    if params.max_event_liability == Amount::ZERO || odd <= 100 {
+        return Amount::MAX;
+    }
+    // Each token staked adds (odd - 100) / 100 to the loss if the selection wins
+    let headroom = u128::from(params.max_event_liability)
+        .saturating_add(u128::from(stakes.total()))
+        .saturating_sub(u128::from(liabilities.get(selection)));
+    Amount::from_attos(headroom.saturating_mul(100) / (odd as u128 - 100))
+}
+
+/// Cuts the odd of every selection the house would lose on, in proportion to how close that
+/// loss is to `max_event_liability`, so new money flows to the other side of the book.
+pub fn skew_for_liability(odds: (u64, u64, u64), stakes: &SelectionStakes, liabilities: &SelectionStakes, params: &ManagementParameters) -> (u64, u64, u64) {
+    let max_liability = u128::from(params.max_event_liability);
+    if max_liability == 0 {
+        return odds;
+    }
+    let skew = |odd: u64, selection: Selection| -> u64 {
+        let loss = u128::from(selection_loss(stakes, liabilities, selection));
+        let cut = ratio(loss, max_liability).min(ONE) * params.liability_skew.min(100) as u128 / 100;
+        let skewed = mul(odd as u128, ONE - cut) as u64;
+        skewed.clamp(params.min_odd, params.max_odd)
+    };
+
+    (
+        skew(odds.0, Selection::Home),
+        skew(odds.1, Selection::Tie),
+        skew(odds.2, Selection::Away)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use linera_sdk::linera_base_types::Amount;
+
+    use super::*;
+
+    /// Implied probabilities of the three odds added up, in percent x100 (10_800 = 108%).
+    fn overround(odds: (u64, u64, u64)) -> u128 {
+        (probability_from_odd(odds.0) + probability_from_odd(odds.1) + probability_from_odd(odds.2)) * 10_000 / ONE
+    }
+
+    fn assert_overround(odds: (u64, u64, u64), params: &ManagementParameters) {
+        let expected = params.margin as u128 * 100;
+        let actual = overround(odds);
+        // Odds are whole hundredths, rounding moves the book by a fraction of a percent
+        assert!(actual.abs_diff(expected) <= 100, "overround {} of {:?}, expected {}", actual, odds, expected);
+    }
+
+    fn team(power: u64, form: i64, goal_average: i64) -> TeamInfo {
+        TeamInfo { power, form, goal_average, ..TeamInfo::default() }
+    }
+
+    /// Xorshift generator with a fixed seed, so a failing case replays on every run.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        /// Uniform enough in `low..=high` for test inputs.
+        fn range(&mut self, low: i64, high: i64) -> i64 {
+            low + (self.next() % (high - low + 1) as u64) as i64
+        }
+
+        fn team(&mut self) -> TeamInfo {
+            team(self.range(0, 1_000) as u64, self.range(-10, 10), self.range(-40, 40))
+        }
+
+        fn stake(&mut self) -> Amount {
+            // Mostly empty or small books, sometimes a huge one-sided bet
+            match self.next() % 4 {
+                0 => Amount::ZERO,
+                1 => Amount::from_tokens(self.range(1, 1_000) as u128),
+                2 => Amount::from_attos(self.next() as u128),
+                _ => Amount::from_tokens(self.range(1, 10_000_000) as u128),
+            }
+        }
+
+        fn match_state(&mut self) -> MatchState {
+            MatchState {
+                minute: self.range(0, 100) as u32,
+                score: (self.range(0, 7) as u8, self.range(0, 7) as u8),
+                red_cards: (self.range(0, 4) as u32, self.range(0, 4) as u32),
+            }
+        }
+    }
+
+    fn assert_book(odds: (u64, u64, u64), params: &ManagementParameters, case: &str) {
+        for odd in [odds.0, odds.1, odds.2] {
+            assert!((params.min_odd..=params.max_odd).contains(&odd), "{} out of bounds in {}: {:?}", odd, case, odds);
+        }
+        assert!(overround(odds) >= 10_000, "overround {} below 100% in {}: {:?}", overround(odds), case, odds);
+    }
+
+    #[test]
+    fn exp_neg_matches_float_exp() {
+        assert_eq!(exp_neg(0), ONE);
+        for x in [0.001f64, 0.25, 0.5, 0.693, 1.0, 2.6, 5.0, 10.0, 30.0] {
+            let actual = exp_neg((x * 1e18) as u128) as f64 / 1e18;
+            let expected = (-x).exp();
+            assert!((actual - expected).abs() < 1e-12, "exp(-{}) = {}, expected {}", x, actual, expected);
+        }
+        assert_eq!(exp_neg(64 * LN_2), 0);
+    }
+
+    #[test]
+    fn exp_neg_is_accurate_across_its_domain() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..10_000 {
+            // Spread the samples over every power of two the result can take
+            let x = rng.range(0, 63) as u128 * LN_2 + rng.next() as u128 % LN_2;
+            let actual = exp_neg(x) as f64 / 1e18;
+            let expected = (-(x as f64 / 1e18)).exp();
+            assert!((actual - expected).abs() < 1e-12, "exp(-{}) = {}, expected {}", x, actual, expected);
+        }
+    }
+
+    #[test]
+    fn random_books_stay_fair_and_bounded() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for margin_params in [ManagementParameters::default(), ManagementParameters { margin: 100, min_odd: 120, max_odd: 800, ..ManagementParameters::default() }] {
+            for _ in 0..2_000 {
+                let params = ManagementParameters { margin: margin_params.margin + rng.range(0, 20) as u64, ..margin_params.clone() };
+                let opening = calculate_odds(&rng.team(), &rng.team(), &params);
+                assert_book(opening, &params, "opening");
+
+                let state = rng.match_state();
+                let live = live_odds(opening, state.score, state.minute, state.red_cards, &params);
+                assert_book(live, &params, "live");
+                let carried = carry_odds(opening, opening, &MatchState::default(), &state, &params);
+                assert_book(carried, &params, "carried");
+
+                let stakes = SelectionStakes { home: rng.stake(), away: rng.stake(), tie: rng.stake() };
+                let market = get_market_odds(live, &stakes, &params);
+                assert_book(market, &params, "market");
+                let liabilities = SelectionStakes { home: rng.stake(), away: rng.stake(), tie: rng.stake() };
+                assert_book(skew_for_liability(market, &stakes, &liabilities, &params), &params, "skewed");
+            }
+        }
+    }
+
+    #[test]
+    fn pre_match_odds_carry_the_margin() {
+        let params = ManagementParameters::default();
+        for (home, away) in [(team(80, 2, 5), team(70, -1, 0)), (team(50, 0, 0), team(50, 0, 0)), (team(60, 3, -2), team(90, 4, 12))] {
+            let odds = calculate_odds(&home, &away, &params);
+            assert_overround(odds, &params);
+        }
+
+        let params = ManagementParameters { margin: 105, ..ManagementParameters::default() };
+        assert_overround(calculate_odds(&team(80, 0, 0), &team(75, 0, 0), &params), &params);
+    }
+
+    #[test]
+    fn market_odds_carry_the_margin_and_follow_the_money() {
+        let params = ManagementParameters::default();
+        let opening = (210, 340, 360);
+        assert_overround(get_market_odds(opening, &SelectionStakes::default(), &params), &params);
+
+        let stakes = SelectionStakes { home: Amount::from_tokens(500), ..SelectionStakes::default() };
+        let odds = get_market_odds(opening, &stakes, &params);
+        assert_overround(odds, &params);
+        let before = get_market_odds(opening, &SelectionStakes::default(), &params);
+        assert!(odds.0 < before.0 && odds.1 > before.1 && odds.2 > before.2);
+    }
+
+    #[test]
+    fn live_odds_carry_the_margin() {
+        let params = ManagementParameters::default();
+        let opening = (210, 340, 360);
+        for (score, minute, red_cards) in [((0, 0), 0, (0, 0)), ((0, 0), 30, (0, 0)), ((1, 0), 20, (0, 0)), ((0, 1), 45, (1, 0)), ((1, 1), 60, (0, 1))] {
+            assert_overround(live_odds(opening, score, minute, red_cards, &params), &params);
+        }
+    }
+
+    #[test]
+    fn live_odds_follow_the_match() {
+        let params = ManagementParameters::default();
+        let opening = (210, 340, 360);
+        let kickoff = live_odds(opening, (0, 0), 0, (0, 0), &params);
+        let home_lead = live_odds(opening, (1, 0), 20, (0, 0), &params);
+        let home_red = live_odds(opening, (0, 0), 20, (1, 0), &params);
+        assert!(home_lead.0 < kickoff.0 && home_lead.2 > kickoff.2);
+        assert!(home_red.0 > live_odds(opening, (0, 0), 20, (0, 0), &params).0);
+        // A goalless draw gets likelier as time runs out
+        assert!(live_odds(opening, (0, 0), 80, (0, 0), &params).1 < kickoff.1);
+    }
+
+    #[test]
+    fn carried_odds_keep_the_override() {
+        let params = ManagementParameters::default();
+        let opening = (210, 340, 360);
+        // Priced at the configured margin, which carrying applies again
+        let override_odds = (
+            odd_from_probability(ONE * 40 / 100, &params),
+            odd_from_probability(ONE * 30 / 100, &params),
+            odd_from_probability(ONE * 30 / 100, &params),
+        );
+        let kickoff = MatchState::default();
+        let same = carry_odds(override_odds, opening, &kickoff, &kickoff, &params);
+        assert!(same.0.abs_diff(override_odds.0) <= 2 && same.1.abs_diff(override_odds.1) <= 2 && same.2.abs_diff(override_odds.2) <= 2);
+
+        let home_lead = MatchState { minute: 20, score: (1, 0), red_cards: (0, 0) };
+        let carried = carry_odds(override_odds, opening, &kickoff, &home_lead, &params);
+        assert_overround(carried, &params);
+        assert!(carried.0 < override_odds.0 && carried.2 > override_odds.2);
+    }
+
+    #[test]
+    fn odds_stay_within_bounds() {
+        let params = ManagementParameters::default();
+        let mismatch = calculate_odds(&team(1_000, 10, 40), &team(1, -10, -40), &params);
+        let late_lead = live_odds((210, 340, 360), (4, 0), 89, (0, 2), &params);
+        for odds in [mismatch, late_lead] {
+            for odd in [odds.0, odds.1, odds.2] {
+                assert!((params.min_odd..=params.max_odd).contains(&odd), "{} out of bounds", odd);
+            }
+        }
+        assert_eq!(late_lead.0, params.min_odd);
+        assert_eq!(late_lead.2, params.max_odd);
+
+        let tight = ManagementParameters { min_odd: 150, max_odd: 400, ..ManagementParameters::default() };
+        assert_eq!(odd_from_probability(ONE * 9 / 10, &tight), 150);
+        assert_eq!(odd_from_probability(ONE / 10, &tight), 400);
+        let stakes = SelectionStakes { away: Amount::from_tokens(1_000_000), ..SelectionStakes::default() };
+        let odds = get_market_odds((210, 340, 360), &stakes, &tight);
+        assert_eq!((odds.0, odds.2), (400, 150));
+    }
+}
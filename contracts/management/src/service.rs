@@ -2,21 +2,22 @@
 
 mod state;
 
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
-use async_graphql::{Object, Schema, Response, EmptySubscription };
+use async_graphql::{Object, Schema, Request, Response, EmptySubscription, ErrorExtensions };
 use futures::stream::{Stream, StreamExt};
 use std::pin::Pin;
 use linera_sdk::{
-    graphql::GraphQLMutationRoot, linera_base_types::{WithServiceAbi, Amount, DataBlobHash}, views::{View}, Service,
+    graphql::GraphQLMutationRoot, linera_base_types::{WithServiceAbi, Amount, AccountOwner, ChainId, DataBlobHash, Timestamp}, views::{View, ViewError}, Service,
     ServiceRuntime
 };
-use management::Operation;
+use management::{ManagementParameters, Operation};
+use usdl::UsdlAbi;
 
-use self::state::{ManagementState,UserVotes, Event, UserOdd, UserOdds, MatchStatus, TypeEvent, BetStatus, BetsSummary, LeaderboardData, TeamInfo};
+use self::state::{ManagementState, BouncedStake, UserVotes, Event, UserOdd, UserOdds, MatchStatus, TypeEvent, BetStatus, BetsSummary, BetFilter, BetHistory, BetHistorySummary, ExportFormat, TransactionPage, MarketRisk, Selection, selection_loss, OddsPoint, LeaderboardData, TeamInfo};
 
 pub struct ManagementService {
-    state: ManagementState,
+    state: Arc<ManagementState>,
     runtime: Arc<ServiceRuntime<Self>>,
 }
 
@@ -27,14 +28,14 @@ impl WithServiceAbi for ManagementService {
 }
 
 impl Service for ManagementService {
-    type Parameters = ();
+    type Parameters = ManagementParameters;
 
     async fn new(runtime: ServiceRuntime<Self>) -> Self {
         let state = ManagementState::load(runtime.root_view_storage_context())
             .await
             .expect("Failed to load state");
         ManagementService {
-            state,
+            state: Arc::new(state),
             runtime: Arc::new(runtime),
         }
     }
@@ -43,175 +44,447 @@ impl Service for ManagementService {
         Schema::build(
             QueryRoot {
                 runtime: self.runtime.clone(),
-                storage_context:  self.runtime.root_view_storage_context()
+                state: self.state.clone(),
             },
             Operation::mutation_root(self.runtime.clone()),
             EmptySubscription
         )   
+        .data(self.state.clone())
         .finish()
         .execute(query)
         .await
     }
 }
 
+/// Error codes set in the `code` extension of the GraphQL errors returned by the queries.
+#[derive(Clone, Copy, Debug)]
+enum QueryError {
+    NotFound,
+    Storage,
+    InvalidArgument,
+    NotConfigured,
+}
+
+impl QueryError {
+    fn code(self) -> &'static str {
+        match self {
+            QueryError::NotFound => "NOT_FOUND",
+            QueryError::Storage => "STORAGE_ERROR",
+            QueryError::InvalidArgument => "INVALID_ARGUMENT",
+            QueryError::NotConfigured => "NOT_CONFIGURED",
+        }
+    }
+
+    fn with_message(self, message: impl Into<String>) -> async_graphql::Error {
+        async_graphql::Error::new(message).extend_with(|_, extensions| extensions.set("code", self.code()))
+    }
+}
+
+fn storage_error(error: ViewError) -> async_graphql::Error {
+    QueryError::Storage.with_message(format!("Failed to read state: {}", error))
+}
+
 struct QueryRoot {
     runtime: Arc<ServiceRuntime<ManagementService>>,
-    storage_context: linera_sdk::views::ViewStorageContext,
+    state: Arc<ManagementState>,
 }
 
 #[Object]
 impl QueryRoot {
-    async fn events(&self) -> Vec<Event> {
-        match ManagementState::load(self.storage_context.clone()).await{
-            Ok(state) => {
-                let mut all_events = Vec::new();
-                match state.events.indices().await {
-                    Ok(event_ids) => {
-                        for event_id in event_ids {
-                            if let Ok(Some(event)) = state.events.get(&event_id).await {
-                                all_events.push(event);
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to get event indices: {:?}", e);
-                    }
-                }
-                all_events
+    /// Events sorted by kickoff, optionally filtered. `after` is the id of the last event of the
+    /// previous page and `first` the page size.
+    async fn events(
+        &self,
+        status: Option<MatchStatus>,
+        league: Option<String>,
+        type_event: Option<TypeEvent>,
+        team_id: Option<String>,
+        start_from: Option<Timestamp>,
+        start_to: Option<Timestamp>,
+        after: Option<String>,
+        first: Option<usize>,
+    ) -> async_graphql::Result<Vec<Event>> {
+        let mut all_events = Vec::new();
+        self.state.events.for_each_index_value(|_, event| {
+            let matches = status.is_none_or(|status| event.status == status)
+                && league.as_ref().is_none_or(|league| &event.league == league)
+                && type_event.is_none_or(|type_event| event.type_event == type_event)
+                && team_id.as_ref().is_none_or(|team_id| &event.teams.home.id == team_id || &event.teams.away.id == team_id)
+                && start_from.is_none_or(|from| event.start_time >= from)
+                && start_to.is_none_or(|to| event.start_time <= to);
+            if matches {
+                all_events.push(event.into_owned());
             }
-            Err(e) => {
-                eprintln!("Failed to load state: {:?}", e);
-                Vec::new()
-            }
-        }
+            Ok(())
+        }).await.map_err(storage_error)?;
+        all_events.sort_by(|a, b| a.start_time.cmp(&b.start_time).then_with(|| a.id.cmp(&b.id)));
+
+        let start = match after {
+            Some(cursor) => all_events.iter().position(|event| event.id == cursor)
+                .map(|position| position + 1)
+                .ok_or_else(|| QueryError::InvalidArgument.with_message(format!("Unknown cursor {}", cursor)))?,
+            None => 0,
+        };
+        Ok(all_events.into_iter().skip(start).take(first.unwrap_or(usize::MAX)).collect())
     }
 
-    async fn event_odds(&self, event_id: String ) -> Vec<UserOdd> {
-        match ManagementState::load(self.storage_context.clone()).await{
-            Ok(state) => {
-                state.event_odds.get(&event_id).await.expect("Event not found").unwrap_or_default()
-            }
-            Err(e) => {
-                eprintln!("Failed to load state: {:?}", e);
-                Vec::new()
+    /// A single event, for polling one match without listing them all.
+    async fn event(&self, id: String) -> async_graphql::Result<Event> {
+        self.state.events.get(&id).await.map_err(storage_error)?
+            .ok_or_else(|| QueryError::NotFound.with_message(format!("Event {} not found", id)))
+    }
+
+    async fn event_odds(&self, event_id: String ) -> async_graphql::Result<Vec<UserOdd>> {
+        if !self.state.events.contains_key(&event_id).await.map_err(storage_error)? {
+            return Err(QueryError::NotFound.with_message(format!("Event {} not found", event_id)));
+        }
+        let count = self.state.event_bet_counts.get(&event_id).await.map_err(storage_error)?.unwrap_or_default();
+        let mut bets = Vec::new();
+        for position in 0..count {
+            if let Some(bet) = self.state.event_bets.get(&(event_id.clone(), position)).await.map_err(storage_error)? {
+                bets.push(bet);
             }
         }
+        Ok(bets)
     }
 
-    async fn my_odds(&self) -> Vec<UserOdds>{
-        match ManagementState::load(self.storage_context.clone()).await{
-            Ok(state) => {
-                state.user_odds.get().clone()
+    /// Price history of an event, oldest first, optionally limited to a time range. With
+    /// `points`, the history is downsampled to about that many evenly spaced points, always
+    /// keeping the latest price.
+    async fn odds_history(&self, event_id: String, from: Option<Timestamp>, to: Option<Timestamp>, points: Option<usize>) -> async_graphql::Result<Vec<OddsPoint>> {
+        if points == Some(0) {
+            return Err(QueryError::InvalidArgument.with_message("points must be positive"));
+        }
+        let Some(log) = self.state.odds_history.try_load_entry(&event_id).await.map_err(storage_error)? else {
+            if self.state.events.contains_key(&event_id).await.map_err(storage_error)? {
+                return Ok(Vec::new());
             }
-            Err(e) => {
-                eprintln!("Failed to load state: {:?}", e);
-                Vec::new()
+            return Err(QueryError::NotFound.with_message(format!("Event {} not found", event_id)));
+        };
+        let history: Vec<OddsPoint> = log.read(..).await.map_err(storage_error)?
+            .into_iter()
+            .filter(|point| from.is_none_or(|from| point.timestamp >= from) && to.is_none_or(|to| point.timestamp <= to))
+            .collect();
+
+        match points {
+            Some(points) if history.len() > points => {
+                let step = history.len().div_ceil(points);
+                let last = history.len() - 1;
+                Ok(history.into_iter().enumerate()
+                    .filter(|(i, _)| i % step == 0 || *i == last)
+                    .map(|(_, point)| point)
+                    .collect())
             }
+            _ => Ok(history),
         }
     }
 
-    async fn user_votes(&self) -> Vec<UserVotes>{
-        match ManagementState::load(self.storage_context.clone()).await{
-            Ok(state) => {
-                state.user_votes.get().clone()
-            }
-            Err(e) => {
-                eprintln!("Failed to load state: {:?}", e);
-                Vec::new()
-            }
+    /// Stakes, payouts owed and worst case loss of the house on an event, for operators.
+    async fn market_risk(&self, event_id: String) -> async_graphql::Result<MarketRisk> {
+        if !self.state.events.contains_key(&event_id).await.map_err(storage_error)? {
+            return Err(QueryError::NotFound.with_message(format!("Event {} not found", event_id)));
         }
+        let stakes = self.state.event_stakes.get(&event_id).await.map_err(storage_error)?.unwrap_or_default();
+        let liabilities = self.state.event_liabilities.get(&event_id).await.map_err(storage_error)?.unwrap_or_default();
+        let max_loss = [Selection::Home, Selection::Tie, Selection::Away].into_iter()
+            .map(|selection| selection_loss(&stakes, &liabilities, selection))
+            .max()
+            .unwrap_or_default();
+        let max_liability = self.runtime.application_parameters().max_event_liability;
+        Ok(MarketRisk {
+            event_id,
+            stakes,
+            liabilities,
+            max_loss,
+            max_liability,
+            remaining: max_liability.saturating_sub(max_loss),
+        })
     }
 
-    async fn balance(&self) -> Amount {
-        match ManagementState::load(self.storage_context.clone()).await{
-            Ok(state) => {
-                state.user_balance.get().clone()
-            }
-            Err(e) => {
-                eprintln!("Failed to load state: {:?}", e);
-                Amount::from_tokens(0)
+    /// Bets placed from this chain, optionally only the open or the settled ones.
+    ///
+    /// Bets of a chain not yet migrated from the first release are read from the legacy vector.
+    async fn my_odds(&self, settled: Option<bool>) -> async_graphql::Result<Vec<UserOdds>> {
+        let bet_ids = match settled {
+            Some(false) => self.state.open_bets.indices().await,
+            Some(true) => self.state.settled_bets.indices().await,
+            None => self.state.user_bets.indices().await,
+        }.map_err(storage_error)?;
+        let mut bets = Vec::new();
+        for bet_id in bet_ids {
+            if let Some(bet) = self.state.user_bets.get(&bet_id).await.map_err(storage_error)? {
+                bets.push(bet);
             }
         }
-    }   
-
-    async fn bets_summary(&self) -> BetsSummary {
-        match ManagementState::load(self.storage_context.clone()).await {
-            Ok(state) => {
-                let all_bets: Vec<UserOdds> = state.user_odds.get().clone();
-                
-                let mut total_staked = 0u128;
-                let mut potential_winnings = 0u128;
-                
-                for bet in all_bets.iter() {
-                    if matches!(bet.status, BetStatus::Placed) {
-                        let bid_amount: u128 = bet.bid.try_into().unwrap_or(0);
-                        total_staked += bid_amount;
-                        // Calculate potential winnings: bid * (odd / 100)
-                        potential_winnings += (bid_amount * (bet.odd as u128)) / 100;
+        bets.extend(self.state.legacy_user_bets().into_iter()
+            .filter(|bet| settled.is_none_or(|settled| settled == (bet.status != BetStatus::Placed))));
+        Ok(bets)
+    }
+
+    /// Bets placed from this chain matching the filter, oldest first, with their aggregates.
+    async fn bet_history(&self, filter: Option<BetFilter>) -> async_graphql::Result<BetHistory> {
+        let bets = self.filtered_bets(filter.unwrap_or_default()).await?;
+        let summary = summarize_bets(&bets);
+        Ok(BetHistory { bets, summary })
+    }
+
+    /// The bet history as a CSV or JSON document, for download. Amounts are in attos, like the summary.
+    async fn export_bets(&self, format: ExportFormat, filter: Option<BetFilter>) -> async_graphql::Result<String> {
+        let bets = self.filtered_bets(filter.unwrap_or_default()).await?;
+        match format {
+            ExportFormat::Json => {
+                let encode_error = |e: serde_json::Error| QueryError::Storage.with_message(format!("Failed to encode bets: {}", e));
+                let summary = summarize_bets(&bets);
+                let mut exported = Vec::with_capacity(bets.len());
+                for bet in &bets {
+                    let mut value = serde_json::to_value(bet).map_err(encode_error)?;
+                    if let Some(fields) = value.as_object_mut() {
+                        fields.remove("bid");
+                        fields.insert("bid_attos".to_string(), u128::from(bet.bid).to_string().into());
+                        fields.insert("returned_attos".to_string(), u128::from(bet_return(bet)).to_string().into());
                     }
+                    exported.push(value);
                 }
-                
-                BetsSummary {
-                    total_staked: total_staked.to_string(),
-                    potential_winnings: potential_winnings.to_string(),
-                }
+                serde_json::to_string(&serde_json::json!({ "bets": exported, "summary": summary })).map_err(encode_error)
             }
-            Err(e) => {
-                eprintln!("Failed to load state: {:?}", e);
-                BetsSummary  {
-                    total_staked: "0".to_string(),
-                    potential_winnings: "0".to_string(),
+            ExportFormat::Csv => {
+                let mut csv = String::from("bet_id,event_id,league,home,away,selection,odd,bid_attos,returned_attos,status,placed_at,start_time\n");
+                for bet in &bets {
+                    csv.push_str(&format!(
+                        "{},{},{},{},{},{:?},{},{},{},{:?},{},{}\n",
+                        bet.bet_id,
+                        csv_field(&bet.event_id),
+                        csv_field(&bet.league),
+                        csv_field(&bet.teams.home.name),
+                        csv_field(&bet.teams.away.name),
+                        bet.selection,
+                        bet.odd,
+                        u128::from(bet.bid),
+                        u128::from(bet_return(bet)),
+                        bet.status,
+                        bet.placed_at.micros(),
+                        bet.start_time.micros(),
+                    ));
                 }
+                Ok(csv)
             }
         }
     }
 
-    async fn bonus_claimed(&self) -> bool {
-        match ManagementState::load(self.storage_context.clone()).await{
-            Ok(state) => {
-                state.bonus_claimed.get().clone()
+    async fn user_votes(&self) -> async_graphql::Result<Vec<UserVotes>> {
+        let mut votes = Vec::new();
+        self.state.user_predictions.for_each_index_value(|_, vote| {
+            votes.push(vote.into_owned());
+            Ok(())
+        }).await.map_err(storage_error)?;
+        Ok(votes)
+    }
+
+    /// USDL balance of an owner on this chain.
+    async fn balance(&self, owner: AccountOwner) -> async_graphql::Result<Amount> {
+        let query = format!("query {{ balance(owner: \"{}\") }}", owner);
+        self.usdl_amount(&query, "balance")
+    }
+
+    async fn bets_summary(&self) -> async_graphql::Result<BetsSummary> {
+        let mut total_staked = 0u128;
+        let mut potential_winnings = 0u128;
+
+        for bet_id in self.state.open_bets.indices().await.map_err(storage_error)? {
+            let Some(bet) = self.state.user_bets.get(&bet_id).await.map_err(storage_error)? else {
+                continue;
+            };
+            if matches!(bet.status, BetStatus::Placed) {
+                let bid_amount: u128 = bet.bid.into();
+                total_staked += bid_amount;
+                // Calculate potential winnings: bid * (odd / 100)
+                potential_winnings += (bid_amount * (bet.odd as u128)) / 100;
             }
-            Err(e) => {
-                eprintln!("Failed to load state: {:?}", e);
-                false
+        }
+        for bet in self.state.legacy_user_bets() {
+            if matches!(bet.status, BetStatus::Placed) {
+                let bid_amount: u128 = bet.bid.into();
+                total_staked += bid_amount;
+                potential_winnings += (bid_amount * (bet.odd as u128)) / 100;
             }
         }
+
+        Ok(BetsSummary {
+            total_staked: total_staked.to_string(),
+            potential_winnings: potential_winnings.to_string(),
+        })
     }
 
-    async fn leaderboard(&self) -> LeaderboardData {
-        match ManagementState::load(self.storage_context.clone()).await{
-            Ok(state) => {
-                state.leaderboard.get().clone()
-            }
-            Err(e) => {
-                eprintln!("Failed to load state: {:?}", e);
-                LeaderboardData::default()
+    async fn bonus_claimed(&self) -> bool {
+        *self.state.bonus_claimed.get()
+    }
+
+    async fn leaderboard(&self) -> async_graphql::Result<LeaderboardData> {
+        let round = self.state.leaderboard_round.get().clone();
+        let mut user_stats = HashMap::new();
+        self.state.user_stats.for_each_index_value(|user_id, stats| {
+            user_stats.insert(user_id, stats.into_owned());
+            Ok(())
+        }).await.map_err(storage_error)?;
+        let mut winners = HashMap::new();
+        self.state.weekly_winners.for_each_index_value(|key, week_winners| {
+            winners.insert(key, week_winners.into_owned());
+            Ok(())
+        }).await.map_err(storage_error)?;
+        Ok(LeaderboardData {
+            week: round.week,
+            year: round.year,
+            winners,
+            user_stats,
+            prize_pool: round.prize_pool,
+        })
+    }
+
+    async fn teams(&self) -> async_graphql::Result<Vec<TeamInfo>> {
+        let mut all_teams = Vec::new();
+        self.state.power_ranking.for_each_index_value(|_, team| {
+            all_teams.push(team.into_owned());
+            Ok(())
+        }).await.map_err(storage_error)?;
+        Ok(all_teams)
+    }
+
+    async fn oracles(&self) -> Vec<ChainId> {
+        self.state.oracles.get().clone()
+    }
+
+    async fn admin(&self) -> Option<AccountOwner> {
+        *self.state.admin.get()
+    }
+
+    async fn config(&self) -> ManagementParameters {
+        self.runtime.application_parameters()
+    }
+
+    /// Ledger of this chain, newest first. `offset` skips the most recent entries and `limit`
+    /// caps the page size.
+    async fn transactions(&self, offset: Option<usize>, limit: Option<usize>) -> async_graphql::Result<TransactionPage> {
+        let total = self.state.ledger.count();
+        let end = total.saturating_sub(offset.unwrap_or(0));
+        let start = end.saturating_sub(limit.unwrap_or(50));
+        let mut entries = self.state.ledger.read(start..end).await.map_err(storage_error)?;
+        entries.reverse();
+        Ok(TransactionPage { total: total as u64, entries })
+    }
+
+    /// Bets and votes from this chain that the management chain rejected.
+    async fn bounced_stakes(&self) -> async_graphql::Result<Vec<BouncedStake>> {
+        self.state.bounced_stakes.read(..).await.map_err(storage_error)
+    }
+
+    /// USDL held by this application on the current chain: escrowed stakes and house funds.
+    async fn treasury(&self) -> async_graphql::Result<Amount> {
+        let owner = AccountOwner::from(self.runtime.application_id().forget_abi());
+        let query = format!("query {{ balance(owner: \"{}\") }}", owner);
+        self.usdl_amount(&query, "balance")
+    }
+}
+
+impl QueryRoot {
+    async fn filtered_bets(&self, filter: BetFilter) -> async_graphql::Result<Vec<UserOdds>> {
+        let matches = |bet: &UserOdds| filter.status.is_none_or(|status| bet.status == status)
+            && filter.league.as_ref().is_none_or(|league| &bet.league == league)
+            && filter.event_id.as_ref().is_none_or(|event_id| &bet.event_id == event_id)
+            && filter.from.is_none_or(|from| bet.placed_at >= from)
+            && filter.to.is_none_or(|to| bet.placed_at <= to);
+        let mut bets = Vec::new();
+        self.state.user_bets.for_each_index_value(|_, bet| {
+            if matches(&bet) {
+                bets.push(bet.into_owned());
             }
+            Ok(())
+        }).await.map_err(storage_error)?;
+        // Chains not yet migrated from the first release keep their bets in the legacy vector.
+        bets.extend(self.state.legacy_user_bets().into_iter().filter(|bet| matches(bet)));
+        bets.sort_by(|a, b| a.placed_at.cmp(&b.placed_at).then_with(|| a.bet_id.cmp(&b.bet_id)));
+        Ok(bets)
+    }
+
+    /// Runs a query against the USDL application and reads an amount in attos from the result.
+    fn usdl_amount(&self, query: &str, field: &str) -> async_graphql::Result<Amount> {
+        let usdl_app_id = self.runtime.application_parameters().usdl_app_id
+            .ok_or_else(|| QueryError::NotConfigured.with_message("USDL application is not configured"))?;
+        let response = self.runtime.query_application(usdl_app_id.with_abi::<UsdlAbi>(), &Request::new(query));
+        if let Some(error) = response.errors.first() {
+            return Err(QueryError::Storage.with_message(format!("USDL query failed: {}", error.message)));
         }
+        let data = response.data.into_json()
+            .map_err(|e| QueryError::Storage.with_message(format!("Failed to read USDL response: {}", e)))?;
+        let attos = data[field].as_str()
+            .and_then(|value| value.parse::<u128>().ok())
+            .ok_or_else(|| QueryError::Storage.with_message(format!("Unexpected USDL response for {}", field)))?;
+        Ok(Amount::from_attos(attos))
     }
+}
 
-    async fn teams(&self) -> Vec<TeamInfo> {
-        match ManagementState::load(self.storage_context.clone()).await{
-            Ok(state) => {
-                let mut all_teams = Vec::new();
-                match state.power_ranking.indices().await {
-                    Ok(ids) => {
-                        for id in ids {
-                            if let Ok(Some(team)) = state.power_ranking.get(&id).await {
-                                all_teams.push(team);
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to get team indices: {:?}", e);
-                    }
-                }
-                all_teams
+/// Amount paid back for a bet: the prize when won, the stake when cancelled.
+fn bet_return(bet: &UserOdds) -> Amount {
+    match bet.status {
+        BetStatus::Won => Amount::from_attos(u128::from(bet.bid) * bet.odd as u128 / 100),
+        BetStatus::Cancelled => bet.bid,
+        BetStatus::Placed | BetStatus::Lost => Amount::ZERO,
+    }
+}
+
+fn summarize_bets(bets: &[UserOdds]) -> BetHistorySummary {
+    let mut summary = BetHistorySummary::default();
+    let mut staked = 0u128;
+    let mut open_stake = 0u128;
+    let mut returned = 0u128;
+    let mut odds_total = 0u128;
+    let (mut win_streak, mut loss_streak) = (0u64, 0u64);
+
+    for bet in bets {
+        let bid = u128::from(bet.bid);
+        match bet.status {
+            BetStatus::Placed => {
+                open_stake += bid;
+                continue;
+            }
+            BetStatus::Cancelled => continue,
+            BetStatus::Won => {
+                summary.wins += 1;
+                win_streak += 1;
+                loss_streak = 0;
+                returned += u128::from(bet_return(bet));
             }
-            Err(e) => {
-                eprintln!("Failed to load state: {:?}", e);
-                Vec::new()
+            BetStatus::Lost => {
+                summary.losses += 1;
+                loss_streak += 1;
+                win_streak = 0;
             }
         }
+        summary.total_bets += 1;
+        staked += bid;
+        odds_total += bet.odd as u128;
+        summary.longest_win_streak = summary.longest_win_streak.max(win_streak);
+        summary.longest_loss_streak = summary.longest_loss_streak.max(loss_streak);
+    }
+
+    let net = returned as i128 - staked as i128;
+    summary.total_staked = staked.to_string();
+    summary.open_stake = open_stake.to_string();
+    summary.total_returned = returned.to_string();
+    summary.net_pnl = net.to_string();
+    if staked > 0 {
+        summary.roi = net as f64 / staked as f64 * 100.0;
+    }
+    if summary.total_bets > 0 {
+        summary.average_odd = odds_total as f64 / summary.total_bets as f64 / 100.0;
     }
-}
\ No newline at end of file
+    summary
+}
+
+/// Quotes a CSV field when it contains a separator, a quote or a line break.
+fn csv_field(value: &str) -> String {
+    if value.contains(|c: char| c == ',' || c == '"' || c == '\n') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -1,29 +1,348 @@
-use linera_sdk::views::{linera_views, RegisterView, RootView, ViewStorageContext, MapView };
-use linera_sdk::linera_base_types::{ChainId, Timestamp, Amount};
+use linera_sdk::views::{linera_views, RegisterView, RootView, ViewError, ViewStorageContext, MapView, LogView, SetView, CollectionView };
+use linera_sdk::linera_base_types::{AccountOwner, ChainId, Timestamp, Amount};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use async_graphql::{SimpleObject, Enum};
+use std::sync::Arc;
+use crate::{odds::calculate_odds, ManagementParameters};
+use async_graphql::{ComplexObject, Context, SimpleObject, Enum, InputObject};
 
 #[derive(RootView, SimpleObject)]
 #[view(context = ViewStorageContext)]
 pub struct ManagementState {
-    pub events: MapView<String, Event>,
-    pub event_odds: MapView<String, Vec<UserOdd>>,
-    pub oracle: RegisterView<Option<ChainId>>,
-    pub token_supp: RegisterView<Amount>,
-    pub leaderboard:RegisterView<LeaderboardData>,// <LeaderboardData>
+    #[graphql(skip)]
+    pub legacy_events: MapView<String, LegacyEvent>, // legacy layout, see migrate_event_suspension
+    #[graphql(skip)]
+    pub event_odds: MapView<String, Vec<LegacyUserOdd>>, // legacy layout, see migrate_legacy_event_storage
+    #[graphql(skip)]
+    pub oracle: RegisterView<Option<ChainId>>, // legacy layout, see migrate_legacy_roles
+    #[graphql(skip)]
+    pub token_supp: RegisterView<Amount>, // legacy layout, the supply is tracked by usdl
+    #[graphql(skip)]
+    pub leaderboard:RegisterView<LeaderboardData>, // legacy layout, see migrate_legacy_event_storage
 
     pub power_ranking: MapView<String, TeamInfo>,
     //state for local instance
-    pub user_odds: RegisterView<Vec<UserOdds>>,
-    pub user_balance: RegisterView<Amount>,
+    #[graphql(skip)]
+    pub user_odds: RegisterView<Vec<LegacyUserOdds>>, // legacy layout, see migrate_legacy_user_history
+    #[graphql(skip)]
+    pub user_balance: RegisterView<Amount>, // legacy layout, play money from before usdl
     pub nonce: RegisterView<u64>,
     pub bonus_claimed: RegisterView<bool>,
-    pub user_votes: RegisterView<Vec<UserVotes>>,
+    #[graphql(skip)]
+    pub user_votes: RegisterView<Vec<UserVotes>>, // legacy layout, see migrate_legacy_user_history
+    // Views are keyed by position: everything above is the first release, only append below
+    pub settlement_cursors: MapView<String, u64>,
+    pub postponed_events: MapView<String, Timestamp>,
+    pub oracles: RegisterView<Vec<ChainId>>,
+    pub admin: RegisterView<Option<AccountOwner>>,
+    pub user_owners: MapView<String, AccountOwner>, // user chain -> owner paid in USDL
+    pub bounced_stakes: LogView<BouncedStake>,
+    pub user_bets: MapView<u64, UserOdds>, // bet id -> bet
+    pub open_bets: SetView<u64>,
+    pub settled_bets: SetView<u64>,
+    pub user_predictions: MapView<u64, UserVotes>, // prediction id -> vote
+    #[graphql(skip)]
+    pub event_bets: MapView<(String, u64), UserOdd>, // (event id, position) -> bet
+    pub event_bet_counts: MapView<String, u64>,
+    #[graphql(skip)]
+    pub bet_positions: MapView<(String, String, u64), u64>, // (event id, user chain, bet id) -> position
+    pub event_stakes: MapView<String, SelectionStakes>,
+    pub leaderboard_round: RegisterView<LeaderboardRound>,
+    pub user_stats: MapView<String, UserStats>, // user chain -> stats for the current week
+    pub weekly_winners: MapView<String, Vec<LeaderboardWinner>>, // "year-week" -> winners
+    #[graphql(skip)]
+    pub live_predictions: MapView<(String, u64), LivePrediction>, // (event id, prediction id) -> market
+    pub event_prediction_ids: MapView<String, Vec<u64>>,
+    #[graphql(skip)]
+    pub prediction_votes: CollectionView<(String, u64), MapView<String, Vote>>, // (event id, prediction id) -> user chain -> vote
+    pub schema_version: RegisterView<u32>, // 0 on chains written before versioning
+    pub ledger: LogView<LedgerEntry>, // money moved in and out of this user chain
+    pub event_liabilities: MapView<String, SelectionStakes>, // event id -> payout owed if each selection wins
+    #[graphql(skip)]
+    pub odds_history: CollectionView<String, LogView<OddsPoint>>, // event id -> every price offered, oldest first
+    pub events: MapView<String, Event>,
+    pub unpaid_payouts: MapView<String, Vec<UnpaidPayout>>, // user chain -> payouts waiting for a known owner
+    #[graphql(skip)]
+    pub pricing_bases: MapView<String, PricingBase>, // event id -> odds the market is priced from
+    #[graphql(skip)]
+    pub pending_bets: MapView<String, Vec<PendingBet>>, // event id -> in-play bets waiting out the bet delay
+    pub goal_suspensions: MapView<String, Timestamp>, // event id -> when a goal suspended the market
+    #[graphql(skip)]
+    pub escrow_receipts: MapView<(String, LedgerReason, String), Amount>, // (user chain, stake reason, reference) -> escrowed and not yet handled
+    pub suspended_markets: SetView<String>, // event ids with betting closed, as streamed to subscribed chains
+    #[graphql(skip)]
+    pub last_incidents: MapView<String, Timestamp>, // event id -> when this chain last saw the score change, a goal or a red card
+}
+
+/// Storage layout written by this build of the application.
+///
+/// Views are keyed by their position in `ManagementState` and stored values are serialized by
+/// position, so neither a view nor a stored type can change in place: keep the old view (with
+/// its old type) where it is as a legacy layout, append the new view at the end, bump this
+/// constant and add a step to `ManagementState::migrate` that moves the data across.
+pub const SCHEMA_VERSION: u32 = 5;
+
+impl ManagementState {
+    /// Upgrades state written by an earlier version of the application to `SCHEMA_VERSION`.
+    /// Called on every contract load; each step runs once per chain, in order, right after the
+    /// application is upgraded.
+    pub async fn migrate(&mut self, params: &ManagementParameters) {
+        let version = *self.schema_version.get();
+        if version >= SCHEMA_VERSION {
+            return;
+        }
+        if version < 1 {
+            self.migrate_legacy_roles();
+            self.migrate_legacy_user_history().await;
+        }
+        if version < 2 {
+            self.migrate_legacy_event_storage().await;
+        }
+        if version < 3 {
+            self.migrate_embedded_predictions().await;
+        }
+        if version < 4 {
+            self.migrate_event_liabilities(params).await;
+        }
+        if version < 5 {
+            self.migrate_event_suspension().await;
+        }
+        self.schema_version.set(SCHEMA_VERSION);
+    }
+
+    /// Version 1: the single oracle of the first release joins the oracle list.
+    pub fn migrate_legacy_roles(&mut self) {
+        if let Some(oracle) = *self.oracle.get() {
+            let mut oracles = self.oracles.get().clone();
+            if !oracles.contains(&oracle) {
+                oracles.push(oracle);
+            }
+            self.oracles.set(oracles);
+            self.oracle.set(None);
+        }
+    }
+
+    /// Bets still kept in the first-release vector of a user chain, with their legacy ids.
+    pub fn legacy_user_bets(&self) -> Vec<UserOdds> {
+        let mut ordinals: HashMap<String, u64> = HashMap::new();
+        self.user_odds.get().iter().map(|legacy| {
+            let ordinal = ordinals.entry(legacy.event_id.clone()).or_default();
+            let bet_id = legacy_bet_id(&legacy.event_id, *ordinal);
+            *ordinal += 1;
+            UserOdds {
+                bet_id,
+                teams: legacy.teams.clone(),
+                league: legacy.league.clone(),
+                start_time: legacy.start_time,
+                odd: legacy.odd,
+                selection: legacy.selection,
+                placed_at: legacy.placed_at,
+                bid: legacy.bid,
+                event_id: legacy.event_id.clone(),
+                status: legacy.status,
+            }
+        }).collect()
+    }
+
+    /// Version 1: bets and votes kept in vectors on user chains move into their maps.
+    ///
+    /// Legacy bets had no id, they get the id of their rank among the chain's bets on the same
+    /// event (see `legacy_bet_id`), which the management chain derives the same way.
+    pub async fn migrate_legacy_user_history(&mut self) {
+        let legacy_bets = self.legacy_user_bets();
+        let legacy_votes = self.user_votes.get().clone();
+        if legacy_bets.is_empty() && legacy_votes.is_empty() {
+            return;
+        }
+
+        for bet in legacy_bets {
+            if bet.status == BetStatus::Placed {
+                let _ = self.open_bets.insert(&bet.bet_id);
+            } else {
+                let _ = self.settled_bets.insert(&bet.bet_id);
+            }
+            let _ = self.user_bets.insert(&bet.bet_id, bet);
+        }
+        for vote in legacy_votes {
+            let _ = self.user_predictions.insert(&vote.id, vote);
+        }
+
+        self.user_odds.set(Vec::new());
+        self.user_votes.set(Vec::new());
+    }
+
+    /// Version 2: per-event bet vectors and the leaderboard register are split into sharded maps.
+    ///
+    /// Legacy bets had no id. A user chain's bets on an event reached the event vector in the
+    /// order they were placed, and only the first ones: once the event left `Scheduled` every
+    /// new bet was reverted. The rank of a bet among its chain's bets on the event is therefore
+    /// the same on both sides, and both derive the id from it (see `legacy_bet_id`).
+    pub async fn migrate_legacy_event_storage(&mut self) {
+        let mut legacy_bets = Vec::new();
+        self.event_odds.for_each_index_value(|event_id, bets| {
+            for bet in bets.into_owned() {
+                legacy_bets.push((event_id.clone(), bet));
+            }
+            Ok(())
+        }).await.expect("Failed to read legacy event bets");
+
+        let mut ordinals: HashMap<(String, String), u64> = HashMap::new();
+        for (event_id, legacy) in legacy_bets {
+            let ordinal = ordinals.entry((legacy.user_id.clone(), event_id.clone())).or_default();
+            let bet = UserOdd {
+                bet_id: legacy_bet_id(&event_id, *ordinal),
+                user_id: legacy.user_id,
+                odd: legacy.odd,
+                selection: legacy.selection,
+                placed_at: legacy.placed_at,
+                bid: legacy.bid,
+                status: BetStatus::Placed,
+                owner: None,
+            };
+            *ordinal += 1;
+
+            let mut stakes = self.event_stakes.get(&event_id).await.expect("Failed to read event stakes").unwrap_or_default();
+            let position = self.event_bet_counts.get(&event_id).await.expect("Failed to read bet count").unwrap_or_default();
+            stakes.add(bet.selection, bet.bid);
+            let _ = self.bet_positions.insert(&(event_id.clone(), bet.user_id.clone(), bet.bet_id), position);
+            let _ = self.event_bets.insert(&(event_id.clone(), position), bet);
+            let _ = self.event_bet_counts.insert(&event_id, position + 1);
+            let _ = self.event_stakes.insert(&event_id, stakes);
+        }
+        self.event_odds.clear();
+
+        let legacy = self.leaderboard.get().clone();
+        if legacy.week == 0 && legacy.year == 0 && legacy.winners.is_empty() && legacy.user_stats.is_empty() && legacy.prize_pool == Amount::ZERO {
+            return;
+        }
+        self.leaderboard_round.set(LeaderboardRound { week: legacy.week, year: legacy.year, prize_pool: legacy.prize_pool });
+        for (user_id, stats) in legacy.user_stats {
+            let _ = self.user_stats.insert(&user_id, stats);
+        }
+        for (key, winners) in legacy.winners {
+            let _ = self.weekly_winners.insert(&key, winners);
+        }
+        self.leaderboard.set(LeaderboardData::default());
+    }
+
+    /// Version 3: prediction markets embedded in the events move into their own views.
+    pub async fn migrate_embedded_predictions(&mut self) {
+        let mut legacy_events = Vec::new();
+        self.legacy_events.for_each_index_value(|event_id, event| {
+            if !event.predictions.is_empty() {
+                legacy_events.push((event_id, event.into_owned()));
+            }
+            Ok(())
+        }).await.expect("Failed to read events");
+
+        for (event_id, mut event) in legacy_events {
+            let predictions = std::mem::take(&mut event.predictions);
+            self.store_predictions(&event_id, predictions.into_iter().map(LivePrediction::from).collect()).await;
+            let _ = self.legacy_events.insert(&event_id, event);
+        }
+    }
+
+    /// Version 4: the payout owed per selection is rebuilt from the bets already placed, and
+    /// events without an odds history get their opening odds back from the team ratings.
+    ///
+    /// The stored odds of those events already lean against the bets taken; repricing from them
+    /// would apply the skew a second time.
+    pub async fn migrate_event_liabilities(&mut self, params: &ManagementParameters) {
+        let mut counts = Vec::new();
+        self.event_bet_counts.for_each_index_value(|event_id, count| {
+            counts.push((event_id, count.into_owned()));
+            Ok(())
+        }).await.expect("Failed to read bet counts");
+
+        for (event_id, count) in counts {
+            let mut liabilities = SelectionStakes::default();
+            for position in 0..count {
+                if let Some(bet) = self.event_bets.get(&(event_id.clone(), position)).await.expect("Failed to read bet") {
+                    if bet.status != BetStatus::Cancelled {
+                        liabilities.add(bet.selection, payout(bet.bid, bet.odd));
+                    }
+                }
+            }
+            let _ = self.event_liabilities.insert(&event_id, liabilities);
+        }
+
+        let mut legacy_events = Vec::new();
+        self.legacy_events.for_each_index_value(|event_id, event| {
+            legacy_events.push((event_id, event.into_owned()));
+            Ok(())
+        }).await.expect("Failed to read events");
+
+        for (event_id, event) in legacy_events {
+            let home = self.power_ranking.get(&event.teams.home.id).await.expect("Failed to read power ranking");
+            let away = self.power_ranking.get(&event.teams.away.id).await.expect("Failed to read power ranking");
+            let (Some(home), Some(away)) = (home, away) else { continue };
+            let history = self.odds_history.load_entry_mut(&event_id).await.expect("Failed to read odds history");
+            if history.count() == 0 {
+                let (home, tie, away) = calculate_odds(&home, &away, params);
+                // The first release kept no creation time
+                history.push(OddsPoint { timestamp: event.last_updated, home, tie, away, reason: OddsChangeReason::Opening, bet_id: None });
+            }
+        }
+    }
+
+    /// Version 5: events gain the `suspended` flag and leave the legacy map.
+    pub async fn migrate_event_suspension(&mut self) {
+        let mut legacy_events = Vec::new();
+        self.legacy_events.for_each_index_value(|event_id, event| {
+            legacy_events.push((event_id, event.into_owned()));
+            Ok(())
+        }).await.expect("Failed to read events");
+
+        for (event_id, event) in legacy_events {
+            let _ = self.events.insert(&event_id, Event::from(event));
+        }
+        self.legacy_events.clear();
+    }
+
+    /// Stores the prediction markets of an event, with their votes, in their own views.
+    async fn store_predictions(&mut self, event_id: &String, predictions: Vec<LivePrediction>) {
+        let mut ids = self.event_prediction_ids.get(event_id).await.expect("Failed to read event predictions").unwrap_or_default();
+        for mut prediction in predictions {
+            let key = (event_id.clone(), prediction.id);
+            let votes = self.prediction_votes.load_entry_mut(&key).await.expect("Failed to read prediction votes");
+            for vote in prediction.votes.drain(..) {
+                let _ = votes.insert(&vote.user.clone(), vote);
+            }
+            if !ids.contains(&prediction.id) {
+                ids.push(prediction.id);
+            }
+            let _ = self.live_predictions.insert(&key, prediction);
+        }
+        let _ = self.event_prediction_ids.insert(event_id, ids);
+    }
+
+    /// Prediction markets of an event, without their votes.
+    pub async fn event_predictions(&self, event_id: &String) -> Result<Vec<LivePrediction>, ViewError> {
+        let ids = self.event_prediction_ids.get(event_id).await?.unwrap_or_default();
+        let mut predictions = Vec::new();
+        for id in ids {
+            if let Some(prediction) = self.live_predictions.get(&(event_id.clone(), id)).await? {
+                predictions.push(prediction);
+            }
+        }
+        Ok(predictions)
+    }
+
+    pub async fn prediction_votes_of(&self, key: &(String, u64)) -> Result<Vec<Vote>, ViewError> {
+        let mut votes = Vec::new();
+        if let Some(entry) = self.prediction_votes.try_load_entry(key).await? {
+            entry.for_each_index_value(|_, vote| {
+                votes.push(vote.into_owned());
+                Ok(())
+            }).await?;
+        }
+        Ok(votes)
+    }
 }
 
 
 #[derive(Clone, Debug, Serialize, Deserialize, SimpleObject, Default)]
+#[graphql(complex)]
 #[serde(rename_all = "camelCase")]
 pub struct Event {
     pub id: String,
@@ -38,18 +357,196 @@ pub struct Event {
     pub match_events: Vec<MatchEvent>,
     pub last_updated: Timestamp,
     pub current_minute: Option<u32>,
-    pub predictions: Vec<LivePrediction>,
+    pub suspended: bool, // no bets are taken while set
+}
+
+/// Event as stored by the first release, before schema version 5.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacyEvent {
+    pub id: String,
+    pub status: MatchStatus,
+    pub type_event: TypeEvent,
+    pub league: String,
+    pub teams: Teams,
+    pub odds: Odds,
+    pub start_time: Timestamp,
+    pub result: LegacyMatchResult,
+    pub live_score: LegacyLiveScore,
+    pub match_events: Vec<MatchEvent>,
+    pub last_updated: Timestamp,
+    pub current_minute: Option<u32>,
+    pub predictions: Vec<LegacyLivePrediction>, // moved to live_predictions by version 3
+}
+
+/// Prediction market embedded in a `LegacyEvent`.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct LegacyLivePrediction {
+    pub id: u64,
+    pub creator: String,
+    pub prediction_type: PredictionType,
+    pub question: String,
+    pub pool_yes: Amount,
+    pub pool_no: Amount,
+    pub resolved: bool,
+    pub outcome: Option<bool>,
+    pub created_at: Timestamp,
+    pub votes: Vec<LegacyVote>,
+}
+
+/// Vote of a `LegacyLivePrediction`, without the paying account.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct LegacyVote {
+    pub user: String,
+    pub amount: Amount,
+    pub choice: bool,
+    pub claimed: bool,
+}
+
+impl From<LegacyLivePrediction> for LivePrediction {
+    fn from(prediction: LegacyLivePrediction) -> Self {
+        LivePrediction {
+            id: prediction.id,
+            creator: prediction.creator,
+            prediction_type: prediction.prediction_type,
+            question: prediction.question,
+            pool_yes: prediction.pool_yes,
+            pool_no: prediction.pool_no,
+            resolved: prediction.resolved,
+            outcome: prediction.outcome,
+            created_at: prediction.created_at,
+            votes: prediction.votes.into_iter()
+                .map(|vote| Vote { user: vote.user, amount: vote.amount, choice: vote.choice, claimed: vote.claimed, owner: None })
+                .collect(),
+        }
+    }
+}
+
+/// Final score of a `LegacyEvent`, with the scores as text.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct LegacyMatchResult {
+    pub winner: Selection,
+    pub home_score: String,
+    pub away_score: String,
+}
+
+/// Live score of a `LegacyEvent`, with the scores as text.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct LegacyLiveScore {
+    pub home: String,
+    pub away: String,
+    pub updated_at: Timestamp,
+}
+
+/// Bet as stored in `user_odds` on user chains by the first release, before schema version 1.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct LegacyUserOdds {
+    pub teams: Teams,
+    pub league: String,
+    pub start_time: Timestamp,
+    pub odd: u64,
+    pub selection: Selection,
+    pub placed_at: Timestamp,
+    pub bid: Amount,
+    pub event_id: String,
+    pub status: BetStatus
+}
+
+/// Bet as stored in `event_odds` by the first release, before schema version 2.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LegacyUserOdd {
+    pub user_id: String,
+    pub odd: u64,
+    pub selection: Selection,
+    pub placed_at: Timestamp,
+    pub bid: Amount,
+}
+
+impl From<LegacyEvent> for Event {
+    fn from(event: LegacyEvent) -> Self {
+        Event {
+            id: event.id,
+            status: event.status,
+            type_event: event.type_event,
+            league: event.league,
+            teams: event.teams,
+            odds: event.odds,
+            start_time: event.start_time,
+            result: MatchResult {
+                winner: event.result.winner,
+                home_score: event.result.home_score.parse().unwrap_or_default(),
+                away_score: event.result.away_score.parse().unwrap_or_default(),
+            },
+            live_score: LiveScore {
+                home: event.live_score.home.parse().unwrap_or_default(),
+                away: event.live_score.away.parse().unwrap_or_default(),
+                updated_at: event.live_score.updated_at,
+            },
+            match_events: event.match_events,
+            last_updated: event.last_updated,
+            current_minute: event.current_minute,
+            suspended: false,
+        }
+    }
+}
+
+impl Event {
+    /// Red cards shown to the home and away sides.
+    pub fn red_cards(&self) -> (u32, u32) {
+        let mut cards = (0, 0);
+        for match_event in self.match_events.iter().filter(|e| e.event_type == MatchEventType::RedCard) {
+            if match_event.team == self.teams.home.name || match_event.team == self.teams.home.id {
+                cards.0 += 1;
+            } else if match_event.team == self.teams.away.name || match_event.team == self.teams.away.id {
+                cards.1 += 1;
+            }
+        }
+        cards
+    }
+
+    /// Point of the match live prices are quoted at.
+    pub fn match_state(&self) -> MatchState {
+        MatchState {
+            minute: self.current_minute.unwrap_or_default(),
+            score: (self.live_score.home, self.live_score.away),
+            red_cards: self.red_cards(),
+        }
+    }
+}
+
+#[ComplexObject]
+impl Event {
+    /// Live prediction markets with their votes, only loaded when the field is requested.
+    async fn predictions(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<LivePrediction>> {
+        let state = ctx.data::<Arc<ManagementState>>()?;
+        let mut predictions = state.event_predictions(&self.id).await?;
+        for prediction in predictions.iter_mut() {
+            prediction.votes = state.prediction_votes_of(&(self.id.clone(), prediction.id)).await?;
+        }
+        Ok(predictions)
+    }
 }
 
 #[derive(Clone, Debug, Copy, Eq, PartialEq, Serialize, Deserialize, Enum, Default)]
+#[graphql(rename_items = "PascalCase")]
 pub enum MatchStatus {
     #[default] Scheduled,
     Live,
     Finished,
     Postponed,
+    Cancelled,
+    Abandoned,
+}
+
+impl MatchStatus {
+    /// Voided events refund every stake instead of settling on a result.
+    pub fn is_void(&self) -> bool {
+        matches!(self, MatchStatus::Cancelled | MatchStatus::Abandoned)
+    }
 }
 
 #[derive(Clone, Debug, Copy, Eq, PartialEq, Serialize, Deserialize, Enum, Default)]
+#[graphql(rename_items = "PascalCase")]
 pub enum TypeEvent {
     #[default] Football,
     Esports,
@@ -57,13 +554,28 @@ pub enum TypeEvent {
 }
 
 #[derive(Clone, Debug, Copy, Eq, PartialEq, Serialize, Deserialize, Enum, Default)]
+#[graphql(rename_items = "PascalCase")]
 pub enum Selection {
     #[default] Home,
     Away,
     Tie,
 }
 
+impl Selection {
+    /// Outcome implied by a final score.
+    pub fn from_score(home_score: u8, away_score: u8) -> Selection {
+        if home_score > away_score {
+            Selection::Home
+        } else if away_score > home_score {
+            Selection::Away
+        } else {
+            Selection::Tie
+        }
+    }
+}
+
 #[derive(Clone, Debug, Copy, Eq, PartialEq, Serialize, Deserialize, Enum, Default)]
+#[graphql(rename_items = "PascalCase")]
 pub enum BetStatus {
     #[default] Placed,
     Won,
@@ -72,6 +584,7 @@ pub enum BetStatus {
 }
 
 #[derive(Clone, Debug, Copy, Eq, PartialEq, Serialize, Deserialize, Enum, Default)]
+#[graphql(rename_items = "PascalCase")]
 pub enum MatchEventType {
     #[default] None,
     Goal,
@@ -124,24 +637,125 @@ pub struct Odds {
     pub tie: u64,
 }
 
+impl Odds {
+    /// Current odd offered for a selection.
+    pub fn for_selection(&self, selection: Selection) -> u64 {
+        match selection {
+            Selection::Home => self.home,
+            Selection::Away => self.away,
+            Selection::Tie => self.tie,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Default, SimpleObject)]
 pub struct MatchResult {
     pub winner: Selection,
-    pub home_score: String,
-    pub away_score: String,
+    pub home_score: u8,
+    pub away_score: u8,
+}
+
+/// An amount per selection of an event: total staked, or payout owed if it wins.
+#[derive(Clone, Debug, Serialize, Deserialize, Default, SimpleObject)]
+pub struct SelectionStakes {
+    pub home: Amount,
+    pub away: Amount,
+    pub tie: Amount,
+}
+
+impl SelectionStakes {
+    pub fn get(&self, selection: Selection) -> Amount {
+        match selection {
+            Selection::Home => self.home,
+            Selection::Away => self.away,
+            Selection::Tie => self.tie,
+        }
+    }
+
+    pub fn total(&self) -> Amount {
+        self.home.saturating_add(self.away).saturating_add(self.tie)
+    }
+
+    pub fn add(&mut self, selection: Selection, amount: Amount) {
+        match selection {
+            Selection::Home => self.home = self.home.saturating_add(amount),
+            Selection::Away => self.away = self.away.saturating_add(amount),
+            Selection::Tie => self.tie = self.tie.saturating_add(amount),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize, Enum)]
+#[graphql(rename_items = "PascalCase")]
+pub enum OddsChangeReason {
+    Opening,
+    Bet,
+    OracleAdjust,
+    InPlay,
+}
+
+/// Odds of an event from `timestamp` until the next point.
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub struct OddsPoint {
+    pub timestamp: Timestamp,
+    pub home: u64,
+    pub tie: u64,
+    pub away: u64,
+    pub reason: OddsChangeReason,
+    pub bet_id: Option<u64>, // bet that moved the price
+}
+
+/// Liability of the house on an event, for operators.
+#[derive(Clone, Debug, SimpleObject)]
+pub struct MarketRisk {
+    pub event_id: String,
+    pub stakes: SelectionStakes,
+    pub liabilities: SelectionStakes, // payout owed if each selection wins
+    pub max_loss: Amount, // worst case over the selections, net of the stakes collected
+    pub max_liability: Amount,
+    pub remaining: Amount, // loss the house can still take before bets are capped
+}
+
+/// Id of the `ordinal`-th bet a user chain placed on `event_id` under the first release.
+///
+/// Legacy bets had no id and a user chain cannot tell which of its bets the management chain
+/// stored (a revert cancelled every bet of the event), so both sides derive the id from the
+/// event and the rank of the bet on it: an FNV-1a hash with bit 52 set, above the ids handed
+/// out from `nonce` and still exact as a JavaScript number.
+pub fn legacy_bet_id(event_id: &str, ordinal: u64) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in event_id.as_bytes().iter().chain(ordinal.to_le_bytes().iter()) {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    (hash & ((1 << 52) - 1)) | (1 << 52)
+}
+
+/// Amount paid for a winning stake at an odd scaled x100.
+pub fn payout(stake: Amount, odd: u64) -> Amount {
+    Amount::from_attos(u128::from(stake).saturating_mul(odd as u128) / 100)
+}
+
+/// House loss if `selection` wins: its payout minus every stake collected on the event.
+pub fn selection_loss(stakes: &SelectionStakes, liabilities: &SelectionStakes, selection: Selection) -> Amount {
+    liabilities.get(selection).saturating_sub(stakes.total())
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
 pub struct UserOdd {
+    pub bet_id: u64,
     pub user_id: String,
     pub odd: u64,
     pub selection: Selection,
     pub placed_at: Timestamp,
     pub bid: Amount,
+    pub status: BetStatus,
+    pub owner: Option<AccountOwner>, // account the stake came from, paid on settlement
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, SimpleObject, Default)]
 pub struct UserOdds {
+    pub bet_id: u64,
     pub teams: Teams,
     pub league: String,
     pub start_time: Timestamp,
@@ -153,16 +767,138 @@ pub struct UserOdds {
     pub status: BetStatus
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize, Enum)]
+#[graphql(rename_items = "PascalCase")]
+pub enum LedgerDirection {
+    Credit,
+    Debit,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize, Enum)]
+#[graphql(rename_items = "PascalCase")]
+pub enum LedgerReason {
+    BetStake,
+    BetWin,
+    BetRefund,
+    PredictionStake,
+    PredictionReward,
+    PredictionRefund,
+    LeaderboardPrize,
+    WelcomeBonus,
+    BouncedStakeRefund,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub struct LedgerEntry {
+    pub direction: LedgerDirection,
+    pub amount: Amount,
+    pub reason: LedgerReason,
+    pub reference: String, // bet id, prediction id or "year-week"
+    pub counterparty: ChainId,
+    pub timestamp: Timestamp,
+}
+
+#[derive(Clone, Debug, SimpleObject)]
+pub struct TransactionPage {
+    pub total: u64,
+    pub entries: Vec<LedgerEntry>, // newest first
+}
+
+/// Payout to a user chain whose owner is not known yet.
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub struct UnpaidPayout {
+    pub amount: Amount,
+    pub reason: LedgerReason,
+    pub reference: String,
+}
+
+/// Point of a match that live prices depend on.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchState {
+    pub minute: u32,
+    pub score: (u8, u8),
+    pub red_cards: (u32, u32),
+}
+
+/// Odds an event's market is priced from, with the match state they were quoted at. Starts
+/// as the opening odds and is replaced by every oracle override.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PricingBase {
+    pub odds: Odds,
+    pub state: MatchState,
+}
+
+/// In-play bet held on the management chain until the bet delay has passed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PendingBet {
+    pub bet_id: u64,
+    pub user_id: ChainId,
+    pub owner: Option<AccountOwner>,
+    pub selection: Selection,
+    pub bid: Amount,
+    pub min_acceptable_odd: u64,
+    pub received_at: Timestamp,
+}
+
+/// Stake message rejected by the management chain, refunded from the escrow.
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub struct BouncedStake {
+    pub kind: String, // bet, vote or prediction
+    pub reference: u64, // bet id or prediction id
+    pub event_id: String,
+    pub amount: Amount,
+    pub bounced_at: Timestamp,
+}
+
 #[derive(Clone, Debug, SimpleObject)]
 pub struct BetsSummary {
     pub total_staked: String,
     pub potential_winnings: String,
 }
 
+/// Filters for the bet history, every field is optional.
+#[derive(Clone, Debug, Default, InputObject)]
+pub struct BetFilter {
+    pub status: Option<BetStatus>,
+    pub league: Option<String>,
+    pub event_id: Option<String>,
+    pub from: Option<Timestamp>, // placed at or after
+    pub to: Option<Timestamp>,   // placed at or before
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Enum)]
+#[graphql(rename_items = "PascalCase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Clone, Debug, Serialize, SimpleObject)]
+pub struct BetHistory {
+    pub bets: Vec<UserOdds>, // oldest first
+    pub summary: BetHistorySummary,
+}
+
+/// Aggregates over the settled (won or lost) bets of a history; amounts are in attos.
+#[derive(Clone, Debug, Default, Serialize, SimpleObject)]
+pub struct BetHistorySummary {
+    pub total_bets: u64,
+    pub wins: u64,
+    pub losses: u64,
+    pub total_staked: String,
+    pub open_stake: String, // bets not settled yet
+    pub total_returned: String,
+    pub net_pnl: String, // signed
+    pub roi: f64, // percent of the settled stake
+    pub average_odd: f64, // decimal odds, e.g. 1.88
+    pub longest_win_streak: u64,
+    pub longest_loss_streak: u64,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Default, SimpleObject)]
 pub struct LiveScore {
-    pub home: String,
-    pub away: String,
+    pub home: u8,
+    pub away: u8,
     pub updated_at: Timestamp,
 }
 
@@ -193,6 +929,14 @@ pub struct LeaderboardWinner {
     pub prize: Amount,
 }
 
+/// Week currently being played and its prize pool.
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject, Default)]
+pub struct LeaderboardRound {
+    pub week: u64,
+    pub year: u64,
+    pub prize_pool: Amount,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, SimpleObject, Default)]
 pub struct LeaderboardData {
     pub week: u64,
@@ -223,7 +967,7 @@ pub struct LivePrediction {
     pub resolved: bool,         
     pub outcome: Option<bool>,  
     pub created_at: Timestamp,
-    pub votes: Vec<Vote>
+    pub votes: Vec<Vote> // stored in prediction_votes, filled in when queried
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, SimpleObject, Default)]
@@ -232,6 +976,7 @@ pub struct Vote {
     pub amount: Amount,           
     pub choice: bool,           
     pub claimed: bool,          
+    pub owner: Option<AccountOwner>, // account the stake came from, paid on settlement
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, SimpleObject, Default)]
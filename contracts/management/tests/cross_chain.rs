@@ -0,0 +1,338 @@
+//! Integration tests of the management application across the management, oracle and user chains.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::time::{Duration, Instant};
+
+use linera_sdk::{
+    linera_base_types::{AccountOwner, Amount, ApplicationId, ChainId, Timestamp},
+    test::{ActiveChain, QueryOutcome, TestValidator},
+};
+use management::{
+    state::{MatchStatus, Selection, TypeEvent},
+    InstantiationArgument, ManagementAbi, ManagementParameters, Operation,
+};
+use usdl::UsdlAbi;
+
+const HOME: &str = "home";
+const AWAY: &str = "away";
+
+/// Management and USDL applications created on the management chain, with one oracle chain.
+struct Deployment {
+    validator: TestValidator,
+    management_chain: ActiveChain,
+    oracle_chain: ActiveChain,
+    usdl_id: ApplicationId<UsdlAbi>,
+    management_id: ApplicationId<ManagementAbi>,
+}
+
+impl Deployment {
+    async fn new() -> Self {
+        let (validator, management_module) =
+            TestValidator::with_current_module::<ManagementAbi, ManagementParameters, InstantiationArgument>().await;
+        let mut management_chain = validator.new_chain().await;
+        let oracle_chain = validator.new_chain().await;
+
+        // The management chain owner is the USDL minter, so the treasury can be minted at creation
+        let admin = AccountOwner::from(management_chain.public_key());
+        let usdl_module = management_chain
+            .publish_bytecode_files_in::<UsdlAbi, (), usdl::InstantiationArgument>("../usdl")
+            .await;
+        let usdl_id = management_chain
+            .create_application(usdl_module, (), usdl::InstantiationArgument { minter: admin }, vec![])
+            .await;
+
+        let parameters = ManagementParameters {
+            usdl_app_id: Some(usdl_id.forget_abi()),
+            ..ManagementParameters::default()
+        };
+        let argument = InstantiationArgument {
+            oracles: vec![oracle_chain.id()],
+            admin,
+            initial_treasury: Amount::from_tokens(100_000),
+        };
+        let management_id = management_chain
+            .create_application(management_module, parameters, argument, vec![usdl_id.forget_abi()])
+            .await;
+
+        Deployment { validator, management_chain, oracle_chain, usdl_id, management_id }
+    }
+
+    /// Rates both teams and creates a scheduled event from `oracle`.
+    async fn create_event(&self, oracle: &ActiveChain, event_id: &str) {
+        oracle
+            .add_block(|block| {
+                for (team_id, power) in [(HOME, 80), (AWAY, 70)] {
+                    block.with_operation(
+                        self.management_id,
+                        Operation::UpdateTeamPower { team_id: team_id.to_string(), name: team_id.to_string(), power, form: 0, goal_average: 0 },
+                    );
+                }
+                block.with_operation(
+                    self.management_id,
+                    Operation::CreateEvent {
+                        id: event_id.to_string(),
+                        type_event: TypeEvent::Football,
+                        league: "League".to_string(),
+                        home_id: HOME.to_string(),
+                        away_id: AWAY.to_string(),
+                        start_time: start_time(),
+                    },
+                );
+            })
+            .await;
+        self.management_chain.handle_received_messages().await;
+    }
+
+    /// New user chain holding the welcome bonus.
+    async fn new_user(&self) -> ActiveChain {
+        let user_chain = self.validator.new_chain().await;
+        user_chain
+            .add_block(|block| {
+                block.with_operation(self.management_id, Operation::RequestMint { amount: Amount::from_tokens(100) });
+            })
+            .await;
+        self.management_chain.handle_received_messages().await;
+        user_chain.handle_received_messages().await;
+        user_chain
+    }
+
+    /// Places bets on the home side of `event_id` in one block and lets the management chain take them.
+    async fn place_bets(&self, user_chain: &ActiveChain, event_id: &str, bid: Amount, count: usize) {
+        self.send_bets(user_chain, event_id, bid, count).await;
+        self.management_chain.handle_received_messages().await;
+        user_chain.handle_received_messages().await;
+    }
+
+    /// Places bets on the home side of `event_id` in one block, not yet received by the management chain.
+    async fn send_bets(&self, user_chain: &ActiveChain, event_id: &str, bid: Amount, count: usize) {
+        user_chain
+            .add_block(|block| {
+                for _ in 0..count {
+                    block.with_operation(self.management_id, bet_on_home(event_id, bid));
+                }
+            })
+            .await;
+    }
+
+    /// Suspends or resumes the market of `event_id` from the oracle chain.
+    async fn set_market_suspended(&self, event_id: &str, suspended: bool) {
+        let event_id = event_id.to_string();
+        let operation = if suspended { Operation::SuspendMarket { event_id } } else { Operation::ResumeMarket { event_id } };
+        self.oracle_chain
+            .add_block(|block| {
+                block.with_operation(self.management_id, operation);
+            })
+            .await;
+        self.management_chain.handle_received_messages().await;
+    }
+
+    /// USDL held by the owner of `chain` on that chain.
+    async fn balance(&self, chain: &ActiveChain) -> Amount {
+        let owner = AccountOwner::from(chain.public_key());
+        let query = format!("query {{ balance(owner: \"{}\") }}", owner);
+        let QueryOutcome { response, .. } = chain.graphql_query(self.usdl_id, query).await;
+        let attos = response["balance"].as_str().expect("Missing balance").parse().expect("Invalid balance");
+        Amount::from_attos(attos)
+    }
+
+    async fn event_status(&self, event_id: &str) -> MatchStatus {
+        let query = format!("query {{ event(id: \"{}\") {{ status }} }}", event_id);
+        let QueryOutcome { response, .. } = self.management_chain.graphql_query(self.management_id, query).await;
+        serde_json::from_value(response["event"]["status"].clone()).expect("Unexpected event status")
+    }
+}
+
+fn start_time() -> Timestamp {
+    Timestamp::from(4_102_444_800_000_000)
+}
+
+fn bet_on_home(event_id: &str, bid: Amount) -> Operation {
+    Operation::PlaceBet {
+        home_id: HOME.to_string(),
+        away_id: AWAY.to_string(),
+        home_name: HOME.to_string(),
+        away_name: AWAY.to_string(),
+        league: "League".to_string(),
+        start_time: start_time(),
+        min_acceptable_odd: 101,
+        selection: Selection::Home,
+        bid,
+        event_id: event_id.to_string(),
+    }
+}
+
+/// Events can only be resolved by registered oracles, a removed one is rejected.
+#[tokio::test(flavor = "multi_thread")]
+async fn removed_oracle_cannot_resolve_events() {
+    let deployment = Deployment::new().await;
+    let former_oracle = deployment.validator.new_chain().await;
+    let management_id = deployment.management_id;
+
+    deployment.management_chain
+        .add_block(|block| {
+            block.with_operation(management_id, Operation::AddOracle { chain_id: former_oracle.id() });
+        })
+        .await;
+    deployment.create_event(&former_oracle, "1").await;
+    assert_eq!(deployment.event_status("1").await, MatchStatus::Scheduled);
+
+    deployment.management_chain
+        .add_block(|block| {
+            block.with_operation(management_id, Operation::RemoveOracle { chain_id: former_oracle.id() });
+        })
+        .await;
+    let certificate = former_oracle
+        .add_block(|block| {
+            block.with_operation(
+                management_id,
+                Operation::ResolveEvent { event_id: "1".to_string(), winner: Selection::Home, home_score: 1, away_score: 0 },
+            );
+        })
+        .await;
+
+    let outcome = deployment.management_chain
+        .try_add_block(|block| {
+            block.with_messages_from(&certificate);
+        })
+        .await;
+    assert!(outcome.is_err(), "A chain that is not an oracle resolved an event");
+    assert_eq!(deployment.event_status("1").await, MatchStatus::Scheduled);
+}
+
+/// Only the admin can change the oracle list or hand the role over.
+#[tokio::test(flavor = "multi_thread")]
+async fn only_the_admin_manages_oracles() {
+    let deployment = Deployment::new().await;
+    let management_id = deployment.management_id;
+    let former_admin = AccountOwner::from(deployment.management_chain.public_key());
+    let new_admin = AccountOwner::from(deployment.oracle_chain.public_key());
+    let other_chain: ChainId = deployment.validator.new_chain().await.id();
+
+    deployment.management_chain
+        .add_block(|block| {
+            block.with_operation(management_id, Operation::TransferAdmin { new_admin });
+        })
+        .await;
+
+    // The management chain owner is no longer the admin
+    let add_oracle = deployment.management_chain
+        .try_add_block(|block| {
+            block.with_operation(management_id, Operation::AddOracle { chain_id: other_chain });
+        })
+        .await;
+    assert!(add_oracle.is_err(), "A non-admin added an oracle");
+    let take_back = deployment.management_chain
+        .try_add_block(|block| {
+            block.with_operation(management_id, Operation::TransferAdmin { new_admin: former_admin });
+        })
+        .await;
+    assert!(take_back.is_err(), "A non-admin transferred the admin role");
+
+    let QueryOutcome { response, .. } = deployment.management_chain
+        .graphql_query(management_id, "query { admin oracles }")
+        .await;
+    assert_eq!(response["admin"], serde_json::json!(new_admin));
+    assert_eq!(response["oracles"], serde_json::json!([deployment.oracle_chain.id()]));
+}
+
+/// A bet claimed twice before the first result arrives is paid once.
+#[tokio::test(flavor = "multi_thread")]
+async fn claiming_twice_pays_once() {
+    let deployment = Deployment::new().await;
+    let management_id = deployment.management_id;
+    deployment.create_event(&deployment.oracle_chain, "1").await;
+    let user_chain = deployment.new_user().await;
+    let bid = Amount::from_tokens(10);
+    deployment.place_bets(&user_chain, "1", bid, 1).await;
+
+    let QueryOutcome { response, .. } = user_chain.graphql_query(management_id, "query { myOdds { odd } }").await;
+    let odd = response["myOdds"][0]["odd"].as_u64().expect("Missing bet");
+
+    deployment.oracle_chain
+        .add_block(|block| {
+            block.with_operation(
+                management_id,
+                Operation::ResolveEvent { event_id: "1".to_string(), winner: Selection::Home, home_score: 2, away_score: 1 },
+            );
+        })
+        .await;
+    deployment.management_chain.handle_received_messages().await;
+
+    let before = deployment.balance(&user_chain).await;
+    // The second claim is sent before the result of the first one reaches the user chain
+    user_chain
+        .add_block(|block| {
+            block.with_operation(management_id, Operation::ClaimReward { bet_id: 0 });
+            block.with_operation(management_id, Operation::ClaimReward { bet_id: 0 });
+        })
+        .await;
+    deployment.management_chain.handle_received_messages().await;
+    user_chain.handle_received_messages().await;
+
+    let prize = Amount::from_attos(u128::from(bid) * odd as u128 / 100);
+    assert_eq!(deployment.balance(&user_chain).await, before.saturating_add(prize));
+    let QueryOutcome { response, .. } = user_chain.graphql_query(management_id, "query { myOdds { status } }").await;
+    assert_eq!(response["myOdds"][0]["status"], "Won");
+}
+
+/// Chains subscribed to the management chain reject bets while the oracle has the market suspended.
+#[tokio::test(flavor = "multi_thread")]
+async fn subscribed_chains_reject_bets_on_suspended_markets() {
+    let deployment = Deployment::new().await;
+    let management_id = deployment.management_id;
+    deployment.create_event(&deployment.oracle_chain, "1").await;
+    let user_chain = deployment.new_user().await;
+    let management_chain_id = deployment.management_chain.id();
+    user_chain
+        .add_block(|block| {
+            block.with_operation(management_id, Operation::Subscribe { chain_id: management_chain_id });
+        })
+        .await;
+
+    deployment.set_market_suspended("1", true).await;
+    user_chain.handle_new_events().await;
+    let rejected = user_chain
+        .try_add_block(|block| {
+            block.with_operation(management_id, bet_on_home("1", Amount::from_tokens(10)));
+        })
+        .await;
+    assert!(rejected.is_err(), "A bet was placed on a suspended market");
+
+    deployment.set_market_suspended("1", false).await;
+    user_chain.handle_new_events().await;
+    deployment.place_bets(&user_chain, "1", Amount::from_tokens(10), 1).await;
+    let QueryOutcome { response, .. } = user_chain.graphql_query(management_id, "query { myOdds { status } }").await;
+    assert_eq!(response["myOdds"][0]["status"], "Placed");
+}
+
+/// Taking a bet costs the same with hundreds of bets on the event as with a handful: the
+/// management chain keeps running totals instead of rereading the event's bets.
+///
+/// Wall-clock timings depend on the machine, so this only runs on demand with `--ignored`.
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "timing benchmark, run with --ignored"]
+async fn bet_cost_stays_flat() {
+    const BATCH: usize = 10;
+    const BATCHES: usize = 30;
+    const SAMPLE: usize = 5;
+
+    let deployment = Deployment::new().await;
+    deployment.create_event(&deployment.oracle_chain, "1").await;
+    let user_chain = deployment.new_user().await;
+
+    let mut timings = Vec::with_capacity(BATCHES);
+    for _ in 0..BATCHES {
+        deployment.send_bets(&user_chain, "1", Amount::from_millis(100), BATCH).await;
+        let start = Instant::now();
+        deployment.management_chain.handle_received_messages().await;
+        timings.push(start.elapsed());
+        user_chain.handle_received_messages().await;
+    }
+
+    // The first batch also warms up the runtime, it is left out
+    let early: Duration = timings[1..=SAMPLE].iter().sum();
+    let late: Duration = timings[BATCHES - SAMPLE..].iter().sum();
+    let per_bet = |total: Duration| total / (SAMPLE * BATCH) as u32;
+    assert!(late < early * 2, "bet cost grew from {:?} to {:?} per bet", per_bet(early), per_bet(late));
+}
@@ -3,14 +3,14 @@
 mod state;
 
 use linera_sdk::{
-    linera_base_types::{WithContractAbi, Amount},
+    linera_base_types::{WithContractAbi, Amount, Account, AccountOwner},
     views::{RootView, View},
     Contract, ContractRuntime,
 };
 
-use usdl::{Message, Operation};
+use usdl::{InstantiationArgument, Message, Operation};
 
-use self::state::UsdlState;
+use self::state::{FailedTransfer, UsdlState, SCHEMA_VERSION};
 
 pub struct UsdlContract {
     state: UsdlState,
@@ -26,71 +26,127 @@ impl WithContractAbi for UsdlContract {
 impl Contract for UsdlContract {
     type Message = Message;
     type Parameters = ();
-    type InstantiationArgument = ();
+    type InstantiationArgument = InstantiationArgument;
     type EventValue = ();
 
-    async fn load(runtime: ContractRuntime<Self>) -> Self {
-        let state = UsdlState::load(runtime.root_view_storage_context())
+    async fn load(mut runtime: ContractRuntime<Self>) -> Self {
+        let mut state = UsdlState::load(runtime.root_view_storage_context())
             .await
             .expect("Failed to load state");
+        if *state.schema_version.get() < SCHEMA_VERSION {
+            let owner = legacy_balance_owner(&mut runtime);
+            state.migrate(owner).await;
+        }
         UsdlContract { state, runtime }
     }
 
-    async fn instantiate(&mut self, _argument: Self::InstantiationArgument) {
+    async fn instantiate(&mut self, argument: Self::InstantiationArgument) {
         // Inicializar el estado del token
         self.state.ticker.set("USDL".to_string());
+        self.state.minter.set(Some(argument.minter));
         self.state.total_supply.set(0);
-        self.state.balance.set(0);
         self.state.nonce.set(0);
     }
 
     async fn execute_operation(&mut self, operation: Self::Operation) -> Self::Response {
         match operation {
-            Operation::Transfer { destination, amount } => {
+            Operation::Transfer { owner, amount, target_account } => {
+                self.check_account_permission(owner);
                 // Verificar que el monto no sea cero
                 assert!(amount != Amount::ZERO, "Cannot transfer zero amount");
-                // Verificar que no sea a la misma chain    
-                assert!(destination != self.runtime.chain_id(), "Cannot transfer to self");
-                
-                let current_balance = *self.state.balance.get();
-                
-                // Verificar que tengamos suficiente balance
-                assert!(current_balance >= amount.into(), "Insufficient balance");
-                
-                let new_balance = current_balance.saturating_sub(amount.into());
-                self.state.balance.set(new_balance);
-
-                // Incrementar nonce para prevenir replay attacks
-                let current_nonce = *self.state.nonce.get();
-                self.state.nonce.set(current_nonce + 1);
-
-                self.runtime
-                    .prepare_message(Message::Receive { amount })
-                    .with_authentication()
-                    .send_to(destination);
+
+                self.debit(owner, amount).await;
+                self.finish_transfer(owner, amount, target_account).await;
+            }
+
+            Operation::Approve { owner, spender, allowance } => {
+                self.check_account_permission(owner);
+                assert!(owner != spender, "Cannot approve self");
+
+                if allowance == Amount::ZERO {
+                    let _ = self.state.allowances.remove(&(owner, spender));
+                } else {
+                    let _ = self.state.allowances.insert(&(owner, spender), allowance.into());
+                }
+            }
+
+            Operation::TransferFrom { owner, spender, amount, target_account } => {
+                self.check_account_permission(spender);
+                assert!(amount != Amount::ZERO, "Cannot transfer zero amount");
+
+                // Consumir la autorización antes de mover los fondos
+                let allowance = self.state.allowances.get(&(owner, spender)).await
+                    .expect("Failed to read allowance")
+                    .unwrap_or_default();
+                assert!(allowance >= amount.into(), "Insufficient allowance");
+                let remaining = allowance - u128::from(amount);
+                if remaining == 0 {
+                    let _ = self.state.allowances.remove(&(owner, spender));
+                } else {
+                    let _ = self.state.allowances.insert(&(owner, spender), remaining);
+                }
+
+                self.debit(owner, amount).await;
+                self.finish_transfer(owner, amount, target_account).await;
             }
 
             Operation::Mint { amount } => {
-                let current_balance = *self.state.balance.get();
-                let new_balance = current_balance.saturating_add(amount.into());
-                self.state.balance.set(new_balance);
+                // Solo el minter, y solo en la chain creadora donde vive el supply
+                assert_eq!(
+                    self.runtime.chain_id(),
+                    self.runtime.application_creator_chain_id(),
+                    "Minting is only allowed on the token creator chain"
+                );
+                // El minter puede ser un owner que firma o una aplicación que llama
+                let minter = (*self.state.minter.get()).expect("Unauthorized: only the minter can mint");
+                self.check_account_permission(minter);
+                assert!(amount != Amount::ZERO, "Cannot mint zero amount");
+
+                self.credit(minter, amount).await;
+
+                let current_supply = *self.state.total_supply.get();
+                self.state.total_supply.set(current_supply.saturating_add(amount.into()));
+            }
+
+            Operation::Burn { owner, amount } => {
+                self.check_account_permission(owner);
+                assert!(amount != Amount::ZERO, "Cannot burn zero amount");
+
+                self.debit(owner, amount).await;
+
+                let creator_chain_id = self.runtime.application_creator_chain_id();
+                if self.runtime.chain_id() == creator_chain_id {
+                    self.burn_supply(amount);
+                } else {
+                    self.runtime
+                        .prepare_message(Message::SupplyBurned { amount })
+                        .with_authentication()
+                        .send_to(creator_chain_id);
+                }
             }
         }
     }
 
     async fn execute_message(&mut self, message: Self::Message) {
         match message {
-            Message::Receive { amount } => {
-                let current_balance = *self.state.balance.get();
-
-                let new_balance = current_balance.saturating_add(amount.into());
-                self.state.balance.set(new_balance);
+            Message::Receive { owner, amount, source } => {
+                // Una transferencia rechazada vuelve a la cuenta de origen
+                if self.runtime.message_is_bouncing() == Some(true) {
+                    self.credit(source, amount).await;
+                    let failure = FailedTransfer {
+                        source,
+                        owner,
+                        chain_id: self.runtime.message_origin_chain_id(),
+                        amount,
+                        failed_at: self.runtime.system_time(),
+                    };
+                    self.state.failed_transfers.push(failure);
+                    return;
+                }
+                self.credit(owner, amount).await;
             },
-            Message::UpdateSupply { amount } => {
-                let current_supply = *self.state.total_supply.get();
-
-                let new_supply = current_supply.saturating_add(amount.into());
-                self.state.total_supply.set(new_supply);
+            Message::SupplyBurned { amount } => {
+                self.burn_supply(amount);
             }
         }
     }
@@ -98,4 +154,123 @@ impl Contract for UsdlContract {
     async fn store(mut self) {
         self.state.save().await.expect("Failed to save state");
     }
-}
\ No newline at end of file
+}
+
+impl UsdlContract {
+    /// An account can be moved by its owner signing the block or by the application it belongs to.
+    fn check_account_permission(&mut self, owner: AccountOwner) {
+        let signer = self.runtime.authenticated_signer();
+        let caller = self.runtime.authenticated_caller_id().map(AccountOwner::from);
+        assert!(
+            signer == Some(owner) || caller == Some(owner),
+            "Unauthorized: operation not signed by the account owner"
+        );
+    }
+
+    /// Credits the target account, locally or through a message to its chain.
+    async fn finish_transfer(&mut self, source: AccountOwner, amount: Amount, target_account: Account) {
+        // Incrementar nonce para prevenir replay attacks
+        let current_nonce = *self.state.nonce.get();
+        self.state.nonce.set(current_nonce + 1);
+
+        if target_account.chain_id == self.runtime.chain_id() {
+            assert!(target_account.owner != source, "Cannot transfer to self");
+            self.credit(target_account.owner, amount).await;
+        } else {
+            self.runtime
+                .prepare_message(Message::Receive { owner: target_account.owner, amount, source })
+                .with_authentication()
+                .with_tracking()
+                .send_to(target_account.chain_id);
+        }
+    }
+
+    async fn debit(&mut self, owner: AccountOwner, amount: Amount) {
+        let current_balance = self.state.balances.get(&owner).await
+            .expect("Failed to read balance")
+            .unwrap_or_default();
+        // Verificar que tengamos suficiente balance
+        assert!(current_balance >= amount.into(), "Insufficient balance");
+
+        let new_balance = current_balance - u128::from(amount);
+        if new_balance == 0 {
+            let _ = self.state.balances.remove(&owner);
+        } else {
+            let _ = self.state.balances.insert(&owner, new_balance);
+        }
+    }
+
+    async fn credit(&mut self, owner: AccountOwner, amount: Amount) {
+        let current_balance = self.state.balances.get(&owner).await
+            .expect("Failed to read balance")
+            .unwrap_or_default();
+        let _ = self.state.balances.insert(&owner, current_balance.saturating_add(amount.into()));
+    }
+
+    /// Removes burned tokens from the supply tracked on the creator chain.
+    fn burn_supply(&mut self, amount: Amount) {
+        let current_supply = *self.state.total_supply.get();
+        self.state.total_supply.set(current_supply.saturating_sub(amount.into()));
+    }
+}
+
+/// Account that inherits the chain-wide balance of the first release: the signer of an
+/// operation, if it is an owner of this chain. Messages carry the signer of the block that sent
+/// them on another chain, so while one is executed the balance stays in the legacy slot.
+fn legacy_balance_owner(runtime: &mut ContractRuntime<UsdlContract>) -> Option<AccountOwner> {
+    if runtime.message_origin_chain_id().is_some() {
+        return None;
+    }
+    let signer = runtime.authenticated_signer()?;
+    let ownership = runtime.chain_ownership();
+    (ownership.super_owners.contains(&signer) || ownership.owners.contains_key(&signer)).then_some(signer)
+}
+
+#[cfg(test)]
+mod tests {
+    use linera_sdk::{
+        linera_base_types::{AccountOwner, ChainId, ChainOwnership, TimeoutConfig},
+        util::BlockingWait,
+        views::View,
+        ContractRuntime,
+    };
+    use std::str::FromStr;
+
+    use super::{legacy_balance_owner, state::UsdlState, UsdlContract};
+
+    const OWNER_A: AccountOwner = AccountOwner::Address20([1; 20]);
+    const OWNER_B: AccountOwner = AccountOwner::Address20([2; 20]);
+    const FOREIGN: AccountOwner = AccountOwner::Address20([3; 20]);
+    const REMOTE_CHAIN: &str = "256e1dbc00482ddd619c293cc0df94d366afe7980022bb22d99e33036fd465dd";
+
+    /// A message signed on another chain arrives before any owner of this multi-owner chain
+    /// signs a block: the legacy balance waits for the owner's operation.
+    #[test]
+    fn foreign_signed_message_leaves_the_legacy_balance() {
+        let mut runtime = ContractRuntime::<UsdlContract>::new()
+            .with_chain_ownership(ChainOwnership::multiple([(OWNER_A, 100), (OWNER_B, 100)], 10, TimeoutConfig::default()))
+            .with_authenticated_signer(FOREIGN)
+            .with_message_origin_chain_id(ChainId::from_str(REMOTE_CHAIN).unwrap());
+        let mut state = UsdlState::load(runtime.root_view_storage_context())
+            .blocking_wait()
+            .expect("Failed to read from mock key value store");
+        state.balance.set(500);
+
+        let owner = legacy_balance_owner(&mut runtime);
+        assert_eq!(owner, None);
+        state.migrate(owner).blocking_wait();
+        assert_eq!(*state.balance.get(), 500);
+        assert_eq!(state.balances.get(&FOREIGN).blocking_wait().unwrap(), None);
+
+        // An operation signed by a non-owner does not take it either
+        runtime.set_message_origin_chain_id(None);
+        assert_eq!(legacy_balance_owner(&mut runtime), None);
+
+        runtime.set_authenticated_signer(OWNER_B);
+        let owner = legacy_balance_owner(&mut runtime);
+        assert_eq!(owner, Some(OWNER_B));
+        state.migrate(owner).blocking_wait();
+        assert_eq!(*state.balance.get(), 0);
+        assert_eq!(state.balances.get(&OWNER_B).blocking_wait().unwrap(), Some(500));
+    }
+}
@@ -1,7 +1,7 @@
 use async_graphql::{Request, Response};
 use linera_sdk::{
     graphql::GraphQLMutationRoot,
-    linera_base_types::{ChainId, ContractAbi, ServiceAbi, Amount},
+    linera_base_types::{Account, AccountOwner, ContractAbi, ServiceAbi, Amount},
 };
 use serde::{Deserialize, Serialize};
 
@@ -17,17 +17,46 @@ impl ServiceAbi for UsdlAbi {
     type QueryResponse = Response;
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct InstantiationArgument {
+    /// Account allowed to mint on the chain where the token was created: an owner signing the
+    /// block, or an application calling this one.
+    pub minter: AccountOwner,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub enum Message {
-    Receive { amount: Amount },
-    UpdateSupply { amount: Amount }
+    /// Credits tokens to an account on the receiving chain. Tracked: if rejected,
+    /// it bounces back and `source` is credited instead.
+    Receive { owner: AccountOwner, amount: Amount, source: AccountOwner },
+    /// Tokens burned on another chain, removed from the supply kept on the creator chain.
+    SupplyBurned { amount: Amount }
 }
 
+/// Accounts are moved by their owner signing the block, or by the application
+/// they belong to when it calls this one.
 #[derive(Debug, Deserialize, Serialize, GraphQLMutationRoot)]
 pub enum Operation {
+    /// Same chain or cross-chain transfer out of `owner`'s account.
     Transfer {
-        destination: ChainId,
+        owner: AccountOwner,
+        amount: Amount,
+        target_account: Account,
+    },
+    /// Lets `spender` move up to `allowance` out of `owner`'s account. Zero revokes it.
+    Approve {
+        owner: AccountOwner,
+        spender: AccountOwner,
+        allowance: Amount,
+    },
+    /// Transfer by an approved spender, consuming its allowance.
+    TransferFrom {
+        owner: AccountOwner,
+        spender: AccountOwner,
         amount: Amount,
+        target_account: Account,
     },
+    /// Only the minter, on the creator chain. Credits the minter's account.
     Mint { amount: Amount },
+    Burn { owner: AccountOwner, amount: Amount },
 }
@@ -4,18 +4,18 @@ mod state;
 
 use std::sync::Arc;
 
-use async_graphql::{EmptySubscription, Object, Schema};
+use async_graphql::{EmptySubscription, Object, Schema, SimpleObject};
 use linera_sdk::{
-    graphql::GraphQLMutationRoot, linera_base_types::WithServiceAbi, views::View, Service,
+    graphql::GraphQLMutationRoot, linera_base_types::{AccountOwner, WithServiceAbi}, views::{View, ViewError}, Service,
     ServiceRuntime,
 };
 
 use usdl::Operation;
 
-use self::state::UsdlState;
+use self::state::{FailedTransfer, UsdlState};
 
 pub struct UsdlService {
-    state: UsdlState,
+    state: Arc<UsdlState>,
     runtime: Arc<ServiceRuntime<Self>>,
 }
 
@@ -33,7 +33,7 @@ impl Service for UsdlService {
             .await
             .expect("Failed to load state");
         UsdlService {
-            state,
+            state: Arc::new(state),
             runtime: Arc::new(runtime),
         }
     }
@@ -41,7 +41,7 @@ impl Service for UsdlService {
     async fn handle_query(&self, query: Self::Query) -> Self::QueryResponse {
         Schema::build(
             QueryRoot {
-                balance: self.state.balance.get().clone().to_string(),
+                state: self.state.clone(),
             },
             Operation::mutation_root(self.runtime.clone()),
             EmptySubscription,
@@ -52,13 +52,58 @@ impl Service for UsdlService {
     }
 }
 
+// views are read by the resolvers that need them, a storage failure fails that field only
+fn storage_error(error: ViewError) -> async_graphql::Error {
+    async_graphql::Error::new(format!("Failed to read state: {}", error))
+}
+
 struct QueryRoot {
+    state: Arc<UsdlState>,
+}
+
+#[derive(SimpleObject)]
+struct AccountEntry {
+    owner: AccountOwner,
     balance: String,
 }
 
 #[Object]
 impl QueryRoot {
-    async fn balance(&self) -> &String {
-        &self.balance
+    async fn ticker(&self) -> &String {
+        self.state.ticker.get()
     }
-}
\ No newline at end of file
+
+    /// Tokens in circulation, only tracked on the creator chain.
+    async fn total_supply(&self) -> String {
+        self.state.total_supply.get().to_string()
+    }
+
+    async fn balance(&self, owner: AccountOwner) -> async_graphql::Result<String> {
+        let balance = self.state.balances.get(&owner).await.map_err(storage_error)?;
+        Ok(balance.unwrap_or_default().to_string())
+    }
+
+    /// Every account with a balance on this chain.
+    async fn accounts(&self) -> async_graphql::Result<Vec<AccountEntry>> {
+        let mut accounts = Vec::new();
+        self.state.balances.for_each_index_value(|owner, balance| {
+            accounts.push(AccountEntry { owner, balance: balance.to_string() });
+            Ok(())
+        }).await.map_err(storage_error)?;
+        Ok(accounts)
+    }
+
+    async fn allowance(&self, owner: AccountOwner, spender: AccountOwner) -> async_graphql::Result<String> {
+        let allowance = self.state.allowances.get(&(owner, spender)).await.map_err(storage_error)?;
+        Ok(allowance.unwrap_or_default().to_string())
+    }
+
+    async fn nonce(&self) -> u64 {
+        *self.state.nonce.get()
+    }
+
+    /// Transfers sent from this chain that were rejected and refunded to their source.
+    async fn failed_transfers(&self) -> async_graphql::Result<Vec<FailedTransfer>> {
+        self.state.failed_transfers.read(..).await.map_err(storage_error)
+    }
+}
@@ -1,10 +1,61 @@
-use linera_sdk::views::{linera_views, RegisterView, RootView, ViewStorageContext};
+use async_graphql::SimpleObject;
+use linera_sdk::linera_base_types::{AccountOwner, Amount, ChainId, Timestamp};
+use linera_sdk::views::{linera_views, LogView, MapView, RegisterView, RootView, ViewStorageContext};
+use serde::{Deserialize, Serialize};
 
 #[derive(RootView)]
 #[view(context = ViewStorageContext)]
 pub struct UsdlState {
     pub ticker: RegisterView<String>,
     pub total_supply: RegisterView<u128>,
+    // legacy layout: one balance for the whole chain, moved into `balances` by `migrate`
     pub balance: RegisterView<u128>,
-    pub nonce: RegisterView<u64>
+    pub nonce: RegisterView<u64>,
+    // views are keyed by position: everything above is the first release, only append below
+    // only set on the creator chain, where all minting happens
+    pub minter: RegisterView<Option<AccountOwner>>,
+    // balances of every owner and application account on this chain
+    pub balances: MapView<AccountOwner, u128>,
+    // (owner, spender) -> amount the spender may still move
+    pub allowances: MapView<(AccountOwner, AccountOwner), u128>,
+    // transfers that bounced back and were re-credited to their source
+    pub failed_transfers: LogView<FailedTransfer>,
+    // 0 on chains written before versioning
+    pub schema_version: RegisterView<u32>,
+}
+
+// storage layout written by this build; when a view or a stored type changes keep the old
+// one in place, append the new view, bump the version and add a step to `migrate`
+pub const SCHEMA_VERSION: u32 = 1;
+
+impl UsdlState {
+    // upgrades state written by an earlier version, called on every contract load; `owner`
+    // receives the chain-wide balance of the first release
+    pub async fn migrate(&mut self, owner: Option<AccountOwner>) {
+        let version = *self.schema_version.get();
+        if version >= SCHEMA_VERSION {
+            return;
+        }
+        // version 1: the chain-wide balance moves to the owner's account
+        let legacy_balance = *self.balance.get();
+        if legacy_balance > 0 {
+            // retried on a later block if the owner is not known yet
+            let Some(owner) = owner else { return };
+            let current_balance = self.balances.get(&owner).await
+                .expect("Failed to read balance")
+                .unwrap_or_default();
+            let _ = self.balances.insert(&owner, current_balance.saturating_add(legacy_balance));
+            self.balance.set(0);
+        }
+        self.schema_version.set(SCHEMA_VERSION);
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, SimpleObject)]
+pub struct FailedTransfer {
+    pub source: AccountOwner,
+    pub owner: AccountOwner,
+    pub chain_id: Option<ChainId>,
+    pub amount: Amount,
+    pub failed_at: Timestamp,
 }
@@ -0,0 +1,99 @@
+//! Integration tests of the USDL supply across the creator chain and another chain.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use linera_sdk::{
+    linera_base_types::{Account, AccountOwner, Amount, ApplicationId},
+    test::{ActiveChain, QueryOutcome, TestValidator},
+};
+use usdl::{InstantiationArgument, Operation, UsdlAbi};
+
+fn owner(chain: &ActiveChain) -> AccountOwner {
+    AccountOwner::from(chain.public_key())
+}
+
+/// Sum of the balances of every account on `chain`.
+async fn chain_balances(chain: &ActiveChain, application_id: ApplicationId<UsdlAbi>) -> u128 {
+    let QueryOutcome { response, .. } = chain.graphql_query(application_id, "query { accounts { balance } }").await;
+    response["accounts"]
+        .as_array()
+        .expect("Missing accounts")
+        .iter()
+        .map(|account| account["balance"].as_str().expect("Missing balance").parse::<u128>().expect("Invalid balance"))
+        .sum()
+}
+
+/// The supply kept on the creator chain matches the balances held on every chain.
+async fn assert_supply_matches(chains: [&ActiveChain; 2], application_id: ApplicationId<UsdlAbi>, expected: Amount) {
+    let QueryOutcome { response, .. } = chains[0].graphql_query(application_id, "query { totalSupply }").await;
+    let total_supply: u128 = response["totalSupply"].as_str().expect("Missing supply").parse().expect("Invalid supply");
+    assert_eq!(total_supply, u128::from(expected));
+
+    let mut balances = 0;
+    for chain in chains {
+        balances += chain_balances(chain, application_id).await;
+    }
+    assert_eq!(balances, total_supply);
+}
+
+/// Minting, transfers and burns on either chain keep the balances adding up to the supply.
+#[tokio::test(flavor = "multi_thread")]
+async fn balances_add_up_to_the_supply() {
+    let (validator, module_id) = TestValidator::with_current_module::<UsdlAbi, (), InstantiationArgument>().await;
+    let mut creator_chain = validator.new_chain().await;
+    let other_chain = validator.new_chain().await;
+    let minter = owner(&creator_chain);
+    let holder = owner(&other_chain);
+    let application_id = creator_chain
+        .create_application(module_id, (), InstantiationArgument { minter }, vec![])
+        .await;
+    let chains = [&creator_chain, &other_chain];
+
+    creator_chain
+        .add_block(|block| {
+            block.with_operation(application_id, Operation::Mint { amount: Amount::from_tokens(1_000) });
+        })
+        .await;
+    assert_supply_matches(chains, application_id, Amount::from_tokens(1_000)).await;
+
+    // Same chain and cross-chain transfers only move tokens around
+    creator_chain
+        .add_block(|block| {
+            block.with_operation(
+                application_id,
+                Operation::Transfer {
+                    owner: minter,
+                    amount: Amount::from_tokens(100),
+                    target_account: Account { chain_id: creator_chain.id(), owner: holder },
+                },
+            );
+            block.with_operation(
+                application_id,
+                Operation::Transfer {
+                    owner: minter,
+                    amount: Amount::from_tokens(300),
+                    target_account: Account { chain_id: other_chain.id(), owner: holder },
+                },
+            );
+        })
+        .await;
+    other_chain.handle_received_messages().await;
+    assert_supply_matches(chains, application_id, Amount::from_tokens(1_000)).await;
+
+    // A burn on the creator chain lowers the supply at once
+    creator_chain
+        .add_block(|block| {
+            block.with_operation(application_id, Operation::Burn { owner: minter, amount: Amount::from_tokens(50) });
+        })
+        .await;
+    assert_supply_matches(chains, application_id, Amount::from_tokens(950)).await;
+
+    // A burn on another chain reaches the supply through `SupplyBurned`
+    other_chain
+        .add_block(|block| {
+            block.with_operation(application_id, Operation::Burn { owner: holder, amount: Amount::from_tokens(120) });
+        })
+        .await;
+    creator_chain.handle_received_messages().await;
+    assert_supply_matches(chains, application_id, Amount::from_tokens(830)).await;
+}
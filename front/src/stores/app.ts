@@ -19,11 +19,11 @@ export const appStore = defineStore('app', () => {
     let subscriptionTimeout: ReturnType<typeof setTimeout> | null = null
 
     // Queries GraphQL
-    const UserBalanceQuery = '{"query":"query{balance}"}'
+    const UserBalanceQuery = '{"query":"query{balance(owner: \\"$OWNER\\")}"}'
     const BonusClaimedQuery = '{"query":"query{bonusClaimed}"}'
     const MintTokensQuery = '{"query":"mutation{requestMint(amount: \\"$AMOUNT\\")}"}'
-    const PlaceBetQuery = '{"query":"mutation{placeBet(homeId: \\"$HOME_ID\\", awayId: \\"$AWAY_ID\\", homeName: \\"$HOME_NAME\\", awayName: \\"$AWAY_NAME\\", league: \\"$LEAGUE\\", startTime: $START_TIME, odd: $ODD, selection: \\"$SELECTION\\", bid: \\"$BID\\", eventId: \\"$EVENT_ID\\")}"}'
-    const ClaimRewardQuery = '{"query":"mutation{claimReward(eventId: \\"$EVENT_ID\\")}"}'
+    const PlaceBetQuery = '{"query":"mutation{placeBet(homeId: \\"$HOME_ID\\", awayId: \\"$AWAY_ID\\", homeName: \\"$HOME_NAME\\", awayName: \\"$AWAY_NAME\\", league: \\"$LEAGUE\\", startTime: $START_TIME, minAcceptableOdd: $ODD, selection: $SELECTION, bid: \\"$BID\\", eventId: \\"$EVENT_ID\\")}"}'
+    const ClaimRewardQuery = '{"query":"mutation{claimReward(betId: $BET_ID)}"}'
     const ClaimPredictionRewardQuery = '{"query":"mutation{claimPredictionReward(predictionId: $PREDICTION_ID, eventId: \\"$EVENT_ID\\")}"}'
 
     // Composables
@@ -162,7 +162,7 @@ export const appStore = defineStore('app', () => {
 
     async function getUserBalance() {
         try {
-            const result = await backend.value.query(UserBalanceQuery)
+            const result = await backend.value.query(UserBalanceQuery.replace('$OWNER', address.value ?? ''))
             const response = JSON.parse(result)
             console.log("Balance obtenido:", response.data?.balance)
             // User requested raw display (likely attos shown as '100')
@@ -231,11 +231,11 @@ export const appStore = defineStore('app', () => {
         }
     }
 
-    async function claimReward(eventId: string) {
+    async function claimReward(betId: number) {
         isTransactionPending.value = true
         try {
-            console.log(`Claiming reward for event ${eventId}`)
-            const query = ClaimRewardQuery.replace('$EVENT_ID', eventId)
+            console.log(`Claiming reward for bet ${betId}`)
+            const query = ClaimRewardQuery.replace('$BET_ID', betId.toString())
 
             const result = await backend.value.query(query)
             const response = JSON.parse(result)
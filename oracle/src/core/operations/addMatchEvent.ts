@@ -10,7 +10,7 @@ export async function addMatchEvent(
     const mutation = `
         mutation(
             $eventId: String!, 
-            $eventType: String!, 
+            $eventType: MatchEventType!, 
             $time: String!, 
             $team: String!, 
             $player: String, 
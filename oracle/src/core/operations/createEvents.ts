@@ -5,7 +5,7 @@ export async function createEvent(event: Event) {
     const url = `${config.serviceUrl}/chains/${config.chainId}/applications/${config.appId}`;
 
     const mutation = `
-        mutation($id: String!, $typeEvent: String!, $league: String!, $homeId: String!, $awayId: String!, $startTime: String!) {
+        mutation($id: String!, $typeEvent: TypeEvent!, $league: String!, $homeId: String!, $awayId: String!, $startTime: String!) {
             createEvent(
                 id: $id,
                 typeEvent: $typeEvent,
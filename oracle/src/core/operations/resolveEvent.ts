@@ -13,7 +13,7 @@ export async function resolveEvent(
     const winnerString = winner.toString();
 
     const mutation = `
-        mutation($eventId: String!, $winner: String!, $homeScore: String!, $awayScore: String!) {
+        mutation($eventId: String!, $winner: Selection!, $homeScore: Int!, $awayScore: Int!) {
             resolveEvent(
                 eventId: $eventId,
                 winner: $winner,
@@ -26,8 +26,8 @@ export async function resolveEvent(
     const variables = {
         eventId,
         winner: winnerString,
-        homeScore,
-        awayScore
+        homeScore: Number(homeScore),
+        awayScore: Number(awayScore)
     };
 
     try {
@@ -0,0 +1,46 @@
+import { config } from '../../config';
+import { GraphQLResponse } from '../types';
+
+export async function suspendMarket(eventId: string): Promise<void> {
+    const url = `${config.serviceUrl}/chains/${config.chainId}/applications/${config.appId}`;
+
+    const mutation = `
+        mutation($eventId: String!) {
+            suspendMarket(eventId: $eventId)
+        }
+    `;
+
+    const variables = {
+        eventId
+    };
+
+    try {
+        const response = await fetch(url, {
+            method: 'POST',
+            headers: {
+                'Content-Type': 'application/json',
+            },
+            body: JSON.stringify({
+                query: mutation,
+                variables
+            })
+        });
+
+        if (!response.ok) {
+            const errorText = await response.text();
+            throw new Error(`HTTP error! status: ${response.status}, body: ${errorText}`);
+        }
+
+        const result: GraphQLResponse = await response.json() as GraphQLResponse;
+
+        if (result.errors) {
+            throw new Error(`GraphQL errors: ${JSON.stringify(result.errors)}`);
+        }
+
+        console.log(`Suspended market for event ${eventId}`);
+
+    } catch (error) {
+        console.error(`Error suspending market for ${eventId}:`, error);
+        throw error;
+    }
+}
@@ -9,7 +9,7 @@ export async function updateEventScore(
     const url = `${config.serviceUrl}/chains/${config.chainId}/applications/${config.appId}`;
 
     const mutation = `
-        mutation($eventId: String!, $homeScore: String!, $awayScore: String!) {
+        mutation($eventId: String!, $homeScore: Int!, $awayScore: Int!) {
             updateEventLiveScore(
                 eventId: $eventId,
                 homeScore: $homeScore,
@@ -20,8 +20,8 @@ export async function updateEventScore(
 
     const variables = {
         eventId,
-        homeScore,
-        awayScore
+        homeScore: Number(homeScore),
+        awayScore: Number(awayScore)
     };
 
     try {
@@ -7,7 +7,7 @@ export async function updateEventStatus(eventId: string, status: MatchStatus): P
     const statusString = status.toString();
 
     const mutation = `
-        mutation($eventId: String!, $status: String!) {
+        mutation($eventId: String!, $status: MatchStatus!) {
             updateEventStatus(
                 eventId: $eventId,
                 status: $status
@@ -3,7 +3,9 @@ export enum MatchStatus {
     Scheduled = "Scheduled",
     Live = "Live",
     Finished = "Finished",
-    Postponed = "Postponed"
+    Postponed = "Postponed",
+    Cancelled = "Cancelled",
+    Abandoned = "Abandoned"
 }
 
 // Match Rust enum: TypeEvent
@@ -61,14 +63,14 @@ export interface Odds {
 // Match Rust struct: MatchResult
 export interface MatchResult {
     winner: Selection;
-    home_score: string;
-    away_score: string;
+    home_score: number;
+    away_score: number;
 }
 
 // Match Rust struct: LiveScore
 export interface LiveScore {
-    home: string;
-    away: string;
+    home: number;
+    away: number;
     updated_at: string;  // Timestamp as string
 }
 
@@ -33,9 +33,13 @@ export async function transformFixtureToEvent(fixture: any, leagueName: string):
             status = MatchStatus.Finished;
             break;
         case 'PST':
+            status = MatchStatus.Postponed;
+            break;
         case 'CANC':
+            status = MatchStatus.Cancelled;
+            break;
         case 'ABD':
-            status = MatchStatus.Postponed;
+            status = MatchStatus.Abandoned;
             break;
         default:
             status = MatchStatus.Scheduled;
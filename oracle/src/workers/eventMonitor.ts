@@ -4,6 +4,8 @@ import { MonitoringJob, FixtureStatusResponse } from './types';
 import { MatchStatus, Selection } from '../core/types';
 import { updateEventStatus } from '../core/operations/updateEventStatus';
 import { updateEventOdds } from '../core/operations/updateEventOdds';
+import { suspendMarket } from '../core/operations/suspendMarket';
+import { resumeMarket } from '../core/operations/resumeMarket';
 import { resolveEvent } from '../core/operations/resolveEvent';
 import { fetchRealOdds } from '../utils/fetchOdds';
 
@@ -104,6 +106,23 @@ export class EventMonitor {
                 await this.resolveFinishedEvent(job, fixture);
                 this.queueManager.removeJob(job.eventId);
             }
+
+            // Cancelled and abandoned matches are voided on chain, nothing left to follow
+            if (newStatus === MatchStatus.Cancelled || newStatus === MatchStatus.Abandoned) {
+                this.queueManager.removeJob(job.eventId);
+                return;
+            }
+        }
+
+        // Close betting while play is stopped, reopen it when the match restarts
+        const interrupted = ['SUSP', 'INT'].includes(fixture.fixture.status.short);
+        if (newStatus === MatchStatus.Live && interrupted !== !!job.marketSuspended) {
+            if (interrupted) {
+                await suspendMarket(job.eventId);
+            } else {
+                await resumeMarket(job.eventId);
+            }
+            this.queueManager.setMarketSuspended(job.eventId, interrupted);
         }
 
         // Update odds if event is live
@@ -118,8 +137,8 @@ export class EventMonitor {
     }
 
     private async resolveFinishedEvent(job: MonitoringJob, fixture: any): Promise<void> {
-        const homeScore = fixture.score.fulltime.home?.toString() || '0';
-        const awayScore = fixture.score.fulltime.away?.toString() || '0';
+        const homeScore: number = fixture.score.fulltime.home ?? 0;
+        const awayScore: number = fixture.score.fulltime.away ?? 0;
 
         let winner: Selection;
         if (homeScore > awayScore) {
@@ -130,7 +149,7 @@ export class EventMonitor {
             winner = Selection.Tie;
         }
 
-        await resolveEvent(job.eventId, winner, homeScore, awayScore);
+        await resolveEvent(job.eventId, winner, homeScore.toString(), awayScore.toString());
         console.log(`Event ${job.eventId} resolved: ${winner} (${homeScore}-${awayScore})`);
     }
 
@@ -144,15 +163,19 @@ export class EventMonitor {
             case '2H':
             case 'ET':
             case 'P':
+            case 'SUSP':
+            case 'INT':
                 return MatchStatus.Live;
             case 'FT':
             case 'AET':
             case 'PEN':
                 return MatchStatus.Finished;
             case 'PST':
+                return MatchStatus.Postponed;
             case 'CANC':
+                return MatchStatus.Cancelled;
             case 'ABD':
-                return MatchStatus.Postponed;
+                return MatchStatus.Abandoned;
             default:
                 return MatchStatus.Scheduled;
         }
@@ -98,6 +98,14 @@ export class QueueManager {
         }
     }
 
+    setMarketSuspended(eventId: string, suspended: boolean): void {
+        const job = this.queue.jobs.find(j => j.eventId === eventId);
+        if (job) {
+            job.marketSuspended = suspended;
+            this.saveQueue();
+        }
+    }
+
     incrementRetry(eventId: string): void {
         const job = this.queue.jobs.find(j => j.eventId === eventId);
         if (job) {
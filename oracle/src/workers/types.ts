@@ -8,6 +8,7 @@ export interface MonitoringJob {
     pollInterval: number;
     retryCount: number;
     league: string;
+    marketSuspended?: boolean;
 }
 
 export interface EventQueue {
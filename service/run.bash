@@ -37,8 +37,10 @@ cd contracts/management
 APP_ID_FILE="$HOME/.config/linera/app_id.txt"
 HASH_FILE="$HOME/.config/linera/contract_hash.txt"
 
-# Calcular hash del código del contrato
-CURRENT_HASH=$(find src -type f -name "*.rs" -exec sha256sum {} \; | sort | sha256sum | awk '{print $1}')
+USDL_APP_ID_FILE="$HOME/.config/linera/usdl_app_id.txt"
+
+# Calcular hash del código de los contratos (management y token USDL)
+CURRENT_HASH=$(find src ../usdl/src -type f -name "*.rs" -exec sha256sum {} \; | sort | sha256sum | awk '{print $1}')
 
 # Verificar si necesitamos redesplegar
 NEED_DEPLOY=false
@@ -60,6 +62,7 @@ else
     else
         echo "Código del contrato sin cambios, reutilizando APP_ID existente"
         export VITE_APP_ID=$(cat "$APP_ID_FILE")
+        export VITE_USDL_APP_ID=$(cat "$USDL_APP_ID_FILE" 2>/dev/null || echo "")
         echo "APP_ID (reutilizado): $VITE_APP_ID"
     fi
 fi
@@ -70,13 +73,49 @@ if [ "$NEED_DEPLOY" = "true" ]; then
     echo "Esto puede tomar varios minutos en el primer deploy..."
     
     # Compilar con optimizaciones incrementales para producción
+    (cd ../usdl && CARGO_INCREMENTAL=1 cargo build --release --target wasm32-unknown-unknown)
     CARGO_INCREMENTAL=1 cargo build --release --target wasm32-unknown-unknown
+
+    # El token USDL guarda los saldos, las apuestas y los premios.
+    # Solo el owner principal puede acuñar, y solo en esta chain.
+    echo "Publicando token USDL en Linera..."
+    export VITE_USDL_APP_ID=$(linera publish-and-create ../usdl/target/wasm32-unknown-unknown/release/usdl_{contract,service}.wasm \
+        --json-argument "{\"minter\":\"$VITE_MAIN_OWNER\"}")
+    echo "USDL_APP_ID: $VITE_USDL_APP_ID"
     
+    # Oráculos autorizados: la chain principal y, opcionalmente, la chain del oráculo
+    ORACLE_CHAINS="\"$VITE_MAIN_CHAIN_ID\""
+    if [ -n "${ORACLE_CHAIN_ID:-}" ]; then
+        ORACLE_CHAINS="$ORACLE_CHAINS,\"$ORACLE_CHAIN_ID\""
+    fi
+    # Fondos iniciales de la casa: pagan el bonus de bienvenida y los premios, no pueden ser 0
+    INITIAL_TREASURY=${INITIAL_TREASURY:-1000000}
+    if [ "$INITIAL_TREASURY" = "0" ]; then
+        echo "INITIAL_TREASURY debe ser mayor que 0" >&2
+        exit 1
+    fi
+    INSTANTIATION_ARGUMENT="{\"oracles\":[$ORACLE_CHAINS],\"admin\":\"$VITE_MAIN_OWNER\",\"initial_treasury\":\"$INITIAL_TREASURY\"}"
+    # Parámetros económicos del mercado (margen, liquidez virtual, límites de cuotas, bonus...)
+    APPLICATION_PARAMETERS=${APPLICATION_PARAMETERS:-"{}"}
+    # La aplicación USDL siempre se enlaza al contrato de management
+    EXTRA_PARAMETERS=$(echo "${APPLICATION_PARAMETERS#*\{}" | sed 's/^[[:space:]]*//')
+    if [ "$EXTRA_PARAMETERS" = "}" ]; then
+        APPLICATION_PARAMETERS="{\"usdl_app_id\":\"$VITE_USDL_APP_ID\"}"
+    else
+        APPLICATION_PARAMETERS="{\"usdl_app_id\":\"$VITE_USDL_APP_ID\",$EXTRA_PARAMETERS"
+    fi
+
     echo "Publicando contrato en Linera..."
-    export VITE_APP_ID=$(linera publish-and-create target/wasm32-unknown-unknown/release/management_{contract,service}.wasm)
+    echo "Argumento de instanciación: $INSTANTIATION_ARGUMENT"
+    echo "Parámetros: $APPLICATION_PARAMETERS"
+    export VITE_APP_ID=$(linera publish-and-create target/wasm32-unknown-unknown/release/management_{contract,service}.wasm \
+        --json-argument "$INSTANTIATION_ARGUMENT" \
+        --json-parameters "$APPLICATION_PARAMETERS" \
+        --required-application-ids "$VITE_USDL_APP_ID")
     
     # Guardar APP_ID y hash para futuros reinicios
     echo "$VITE_APP_ID" > "$APP_ID_FILE"
+    echo "$VITE_USDL_APP_ID" > "$USDL_APP_ID_FILE"
     echo "$CURRENT_HASH" > "$HASH_FILE"
     
     echo "APP_ID (nuevo): $VITE_APP_ID"
@@ -113,6 +152,7 @@ echo "Exportando variables al volumen compartido..."
 mkdir -p /shared
 cat > /shared/env.sh << EOF
 export VITE_APP_ID="$VITE_APP_ID"
+export VITE_USDL_APP_ID="$VITE_USDL_APP_ID"
 export VITE_MAIN_CHAIN_ID="$VITE_MAIN_CHAIN_ID"
 export VITE_APP_SERVICE="http://localhost:8081"
 export APPID="$VITE_APP_ID"